@@ -0,0 +1,145 @@
+// Shared between `blit.frag`, `blit_msaa.frag` and `blit_fxaa.frag`, which only differ in how
+// they read the intermediate buffer
+
+layout(location = 0) in vec2 i_UV;
+layout(location = 1) in vec4 i_Color;
+layout(location = 2) flat in uint i_Mode;
+layout(location = 3) flat in uint i_Paint;
+layout(location = 4) flat in uint i_Spread;
+layout(location = 5) flat in vec4 i_Gradient;
+layout(location = 6) flat in uint i_Ramp;
+// In screen coordinates, like the points of gradients
+layout(location = 7) in vec2 i_Position;
+layout(location = 8) flat in vec3 i_PatternX;
+layout(location = 9) flat in vec3 i_PatternY;
+layout(location = 10) flat in uvec4 i_Image;
+layout(location = 11) flat in uint i_Filter;
+// The channel of the intermediate buffer that holds this path
+layout(location = 12) flat in uint i_Channel;
+
+layout(location = 0) out vec4 o_Color;
+
+layout(set = 0, binding = 0) uniform sampler u_Color;
+layout(set = 0, binding = 2) uniform texture2D u_Ramps;
+layout(set = 0, binding = 3) uniform texture2D u_Images;
+
+// These must match `pipelines::blit::Mode`
+#define MODE_STROKE 0
+#define MODE_NONZERO 1
+#define MODE_EVENODD 2
+
+// These must match `pipelines::blit::PaintKind`
+#define PAINT_SOLID 0
+#define PAINT_LINEAR 1
+#define PAINT_RADIAL 2
+#define PAINT_PATTERN 3
+
+// These must match `paint::Spread`
+#define SPREAD_PAD 0
+#define SPREAD_REPEAT 1
+#define SPREAD_REFLECT 2
+
+// These must match `paint::Filter`
+#define FILTER_NEAREST 0
+#define FILTER_LINEAR 1
+
+// Converts the value accumulated in a channel of the intermediate buffer to coverage, which the
+// premultiplied colour of the path is scaled by
+float coverage(float alpha, uint mode) {
+    // For fills, the channel holds the winding number
+    if (mode == MODE_NONZERO) {
+        return min(abs(alpha), 1);
+    } else if (mode == MODE_EVENODD) {
+        return 1 - abs(1 - mod(abs(alpha), 2));
+    } else {
+        return min(max(alpha, 0), 1);
+    }
+}
+
+// Maps a position along a gradient onto 0 to 1, as `Spread::apply` does
+float spread(float t) {
+    if (i_Spread == SPREAD_REPEAT) {
+        return fract(t);
+    } else if (i_Spread == SPREAD_REFLECT) {
+        return 1 - abs(mod(t, 2) - 1);
+    } else {
+        return clamp(t, 0, 1);
+    }
+}
+
+// Maps the index of a texel onto 0 to `size - 1`, as `Spread::wrap` does
+int wrap(int i, int size) {
+    if (i_Spread == SPREAD_REPEAT) {
+        return ((i % size) + size) % size;
+    } else if (i_Spread == SPREAD_REFLECT) {
+        int j = ((i % (2 * size)) + 2 * size) % (2 * size);
+
+        return j < size ? j : 2 * size - 1 - j;
+    } else {
+        return clamp(i, 0, size - 1);
+    }
+}
+
+vec4 image_texel(ivec2 texel) {
+    ivec2 size = ivec2(i_Image.zw);
+    ivec2 wrapped = ivec2(wrap(texel.x, size.x), wrap(texel.y, size.y));
+
+    return texelFetch(sampler2D(u_Images, u_Color), ivec2(i_Image.xy) + wrapped, 0);
+}
+
+// The colour of a pattern, filtered by hand so that it wraps within its image rather than
+// bleeding into its neighbours in the texture, as in `paint::sample_image`
+vec4 pattern() {
+    vec3 position = vec3(i_Position, 1);
+    vec2 p = vec2(dot(i_PatternX, position), dot(i_PatternY, position));
+
+    if (i_Filter == FILTER_NEAREST) {
+        return image_texel(ivec2(floor(p)));
+    }
+
+    vec2 q = p - 0.5;
+    ivec2 texel = ivec2(floor(q));
+    vec2 f = q - floor(q);
+
+    return mix(
+        mix(image_texel(texel), image_texel(texel + ivec2(1, 0)), f.x),
+        mix(image_texel(texel + ivec2(0, 1)), image_texel(texel + ivec2(1, 1)), f.x),
+        f.y
+    );
+}
+
+// The premultiplied colour of the path at this fragment. Gradients interpolate between the two
+// nearest colours in their row of the ramp texture, as in `paint::sample_ramp`.
+vec4 paint() {
+    if (i_Paint == PAINT_SOLID) {
+        return i_Color;
+    } else if (i_Paint == PAINT_PATTERN) {
+        return pattern();
+    }
+
+    // Gradients with no length are at 0 everywhere rather than dividing by zero, as in
+    // `Gradient::position`
+    float t = 0;
+
+    if (i_Paint == PAINT_LINEAR) {
+        vec2 direction = i_Gradient.zw - i_Gradient.xy;
+        float length2 = dot(direction, direction);
+
+        if (length2 > 0) {
+            t = dot(i_Position - i_Gradient.xy, direction) / length2;
+        }
+    } else if (i_Gradient.z > 0) {
+        t = length(i_Position - i_Gradient.xy) / i_Gradient.z;
+    }
+
+    int width = textureSize(sampler2D(u_Ramps, u_Color), 0).x;
+    float x = spread(t) * (width - 1);
+    int i = min(int(floor(x)), width - 2);
+    int row = int(i_Ramp);
+
+    return mix(
+        texelFetch(sampler2D(u_Ramps, u_Color), ivec2(i, row), 0),
+        texelFetch(sampler2D(u_Ramps, u_Color), ivec2(i + 1, row), 0),
+        x - i
+    );
+}
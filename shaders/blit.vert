@@ -4,10 +4,45 @@
 
 layout(location = 0) in vec2 i_Position;
 layout(location = 1) in vec2 i_UV;
+layout(location = 2) in vec4 i_Color;
+layout(location = 3) in uint i_Mode;
+layout(location = 4) in uint i_Paint;
+layout(location = 5) in uint i_Spread;
+layout(location = 6) in vec4 i_Gradient;
+layout(location = 7) in uint i_Ramp;
+layout(location = 8) in vec3 i_PatternX;
+layout(location = 9) in vec3 i_PatternY;
+layout(location = 10) in uvec4 i_Image;
+layout(location = 11) in uint i_Filter;
+layout(location = 12) in uint i_Channel;
 
 layout(location = 0) out vec2 o_UV;
+layout(location = 1) out vec4 o_Color;
+layout(location = 2) flat out uint o_Mode;
+layout(location = 3) flat out uint o_Paint;
+layout(location = 4) flat out uint o_Spread;
+layout(location = 5) flat out vec4 o_Gradient;
+layout(location = 6) flat out uint o_Ramp;
+layout(location = 7) out vec2 o_Position;
+layout(location = 8) flat out vec3 o_PatternX;
+layout(location = 9) flat out vec3 o_PatternY;
+layout(location = 10) flat out uvec4 o_Image;
+layout(location = 11) flat out uint o_Filter;
+layout(location = 12) flat out uint o_Channel;
 
 void main() {
     gl_Position = vec4(i_Position, 0., 1.);
     o_UV = i_UV;
+    o_Color = i_Color;
+    o_Mode = i_Mode;
+    o_Paint = i_Paint;
+    o_Spread = i_Spread;
+    o_Gradient = i_Gradient;
+    o_Ramp = i_Ramp;
+    o_Position = i_Position;
+    o_PatternX = i_PatternX;
+    o_PatternY = i_PatternY;
+    o_Image = i_Image;
+    o_Filter = i_Filter;
+    o_Channel = i_Channel;
 }
@@ -0,0 +1,71 @@
+#version 450
+#pragma shader_stage(fragment)
+#pragma optimize(on)
+
+#include <blit.glsl>
+
+layout(set = 0, binding = 1) uniform texture2D u_Diffuse;
+
+// The tuning constants from the original FXAA: the least and the fraction of the average
+// coverage that the search direction is padded by, and its furthest reach in pixels
+#define FXAA_REDUCE_MIN (1. / 128.)
+#define FXAA_REDUCE_MUL (1. / 8.)
+#define FXAA_SPAN_MAX 8.
+
+float texel_coverage(ivec2 texel) {
+    ivec2 size = textureSize(sampler2D(u_Diffuse, u_Color), 0);
+    ivec2 clamped = clamp(texel, ivec2(0), size - 1);
+
+    return coverage(texelFetch(sampler2D(u_Diffuse, u_Color), clamped, 0)[i_Channel], i_Mode);
+}
+
+// Coverage is interpolated after converting each texel, as interpolating the alpha first would
+// turn the edge between a winding number of 0 and 2 into coverage with the even-odd rule
+float coverage_at(vec2 position) {
+    vec2 p = position - 0.5;
+    ivec2 texel = ivec2(floor(p));
+    vec2 f = p - floor(p);
+
+    return mix(
+        mix(texel_coverage(texel), texel_coverage(texel + ivec2(1, 0)), f.x),
+        mix(texel_coverage(texel + ivec2(0, 1)), texel_coverage(texel + ivec2(1, 1)), f.x),
+        f.y
+    );
+}
+
+// FXAA on the coverage of this draw rather than the colour of the finished image, so the edges
+// it finds are the edges of the path. The search direction runs along the edge, found from the
+// corners around this pixel, and the coverage is averaged along it.
+void main() {
+    vec2 position = i_UV * textureSize(sampler2D(u_Diffuse, u_Color), 0);
+    ivec2 texel = ivec2(floor(position));
+
+    float nw = texel_coverage(texel + ivec2(-1, -1));
+    float ne = texel_coverage(texel + ivec2(1, -1));
+    float sw = texel_coverage(texel + ivec2(-1, 1));
+    float se = texel_coverage(texel + ivec2(1, 1));
+    float m = texel_coverage(texel);
+
+    float lowest = min(m, min(min(nw, ne), min(sw, se)));
+    float highest = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 direction = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce = max((nw + ne + sw + se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float scale = 1. / (min(abs(direction.x), abs(direction.y)) + reduce);
+
+    direction = clamp(direction * scale, -FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+
+    float near = 0.5 * (
+        coverage_at(position + direction * (1. / 3. - 0.5)) +
+        coverage_at(position + direction * (2. / 3. - 0.5))
+    );
+    float far = near * 0.5 + 0.25 * (
+        coverage_at(position + direction * -0.5) +
+        coverage_at(position + direction * 0.5)
+    );
+
+    // The wider average overshoots where the search runs off the end of the edge
+    float result = far < lowest || far > highest ? near : far;
+
+    o_Color = paint() * result;
+}
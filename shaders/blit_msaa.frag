@@ -0,0 +1,26 @@
+#version 450
+#pragma shader_stage(fragment)
+#pragma optimize(on)
+
+#include <blit.glsl>
+
+layout(set = 0, binding = 1) uniform texture2DMS u_Diffuse;
+
+// The multisampled intermediate buffer is resolved here rather than by the hardware, as each
+// sample has to be converted to coverage on its own. Averaging the alpha first would let the
+// negative alpha in one sample cancel out the coverage of another.
+void main() {
+    ivec2 size = textureSize(sampler2DMS(u_Diffuse, u_Color));
+    ivec2 texel = min(ivec2(i_UV * size), size - 1);
+    int samples = textureSamples(sampler2DMS(u_Diffuse, u_Color));
+
+    float total = 0;
+
+    for (int i = 0; i < samples; i++) {
+        float alpha = texelFetch(sampler2DMS(u_Diffuse, u_Color), texel, i)[i_Channel];
+
+        total += coverage(alpha, i_Mode);
+    }
+
+    o_Color = paint() * (total / samples);
+}
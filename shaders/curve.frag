@@ -6,21 +6,48 @@ layout(location = 0) in vec2 i_UV;
 layout(location = 1) in float i_Sign;
 layout(location = 2) in float i_Alpha;
 
+// The same value goes to every channel, and the pipeline only writes the one for this draw
 layout(location = 0) out vec4 o_Color;
 
-// TODO: Have this be either per-vertex or per-invocation
-#define color vec3(1., 1., 1.)
+// Stops the distances below from dividing by zero where the gradient vanishes
+#define MIN_GRADIENT 1e-6
 
-vec4 ifGt(float a, float b, vec4 ifTrue, vec4 ifFalse) {
-    float gt = step(a, b);
-
-    return ifFalse * gt + ifTrue * (1 - gt);
+// The coverage of a pixel whose centre is `distance` pixels outside of an edge, which fades out
+// over the pixel past it. The fade is entirely outside so that the quads of curves, which reach
+// the offset curves at their corners, never cover the part of it that should be faded.
+float coverage(float distance) {
+    return clamp(1 - distance, 0, 1);
 }
 
 void main() {
-    float distance = i_UV.x * i_UV.x - i_UV.y;
-    float discriminant = i_Sign * distance;
+    // Derivatives have to be taken outside of the branches below
+    vec2 du = vec2(dFdx(i_UV.x), dFdy(i_UV.x));
+    vec2 dv = vec2(dFdx(i_UV.y), dFdy(i_UV.y));
+
+    if (i_Sign == 0) {
+        // Quads have the distance across the stroke in `v`, which is -1 and 1 at its edges. The
+        // quads of curves have `v == 0`, so are never faded.
+        float distance = (abs(i_UV.y) - 1) / max(length(dv), MIN_GRADIENT);
+
+        o_Color = vec4(coverage(distance));
+    } else {
+        // The first-order approximation of the distance to the curve `u*u - v == 0`, in pixels,
+        // from the screen-space gradient of the implicit function (Loop-Blinn)
+        float implicit = i_UV.x * i_UV.x - i_UV.y;
+        vec2 gradient = 2 * i_UV.x * du - dv;
+        float distance = i_Sign * implicit / max(length(gradient), MIN_GRADIENT);
+        float inside = coverage(distance);
+
+        // Strokes grow the triangle `(0, 0), (0.5, 0), (1, 1)` to leave room for the fade. Past
+        // the edge, the triangle itself removes coverage from anything else that overlaps it,
+        // while the extra space only adds the fade between the ends of the curve.
+        bool in_triangle = i_UV.y >= 0 && i_UV.y >= 2 * i_UV.x - 1 && i_UV.y <= i_UV.x;
+        bool in_range = i_UV.x >= 0 && i_UV.x <= 1;
 
-    // This ensures that if `i_Sign == 0` we always fill with colour (so we can use the same shader for everything)
-    o_Color = ifGt(discriminant, 0, vec4(color, -2.), vec4(color, 1.));
+        if (in_triangle) {
+            o_Color = vec4(inside > 0 ? inside : -2.);
+        } else {
+            o_Color = vec4(in_range ? inside : 0.);
+        }
+    }
 }
@@ -0,0 +1,41 @@
+#version 450
+#pragma shader_stage(fragment)
+#pragma optimize(on)
+
+layout(location = 0) in vec2 i_UV;
+layout(location = 1) in float i_Sign;
+layout(location = 2) in float i_Alpha;
+
+// The same value goes to every channel, as in `curve.frag`
+layout(location = 0) out vec4 o_Color;
+
+// Stops the distance below from dividing by zero where the gradient vanishes
+#define MIN_GRADIENT 1e-6
+
+void main() {
+    // Derivatives have to be taken outside of the branch below
+    vec2 du = vec2(dFdx(i_UV.x), dFdy(i_UV.x));
+    vec2 dv = vec2(dFdx(i_UV.y), dFdy(i_UV.y));
+
+    float inside = 1;
+
+    // Fan triangles have `i_Sign == 0` and are always inside. Curve triangles fade out over the
+    // pixel centred on the curve, using the same first-order distance as `curve.frag`. As the
+    // fade is centred, it's in the right place whichever way the triangle faces, so whether it
+    // adds to or removes from the fan under it.
+    //
+    // The fade is only drawn inside the curve triangle, so it's cut off where the curve is
+    // within half a pixel of the chord, as it is near its ends. There, and along straight edges,
+    // the edge is the hard edge of the fan, which is left to multisampling or FXAA.
+    if (i_Sign != 0) {
+        float implicit = i_UV.x * i_UV.x - i_UV.y;
+        vec2 gradient = 2 * i_UV.x * du - dv;
+        float distance = implicit / max(length(gradient), MIN_GRADIENT);
+
+        inside = clamp(0.5 - distance, 0, 1);
+    }
+
+    float winding = gl_FrontFacing ? 1. : -1.;
+
+    o_Color = vec4(winding * inside);
+}
@@ -0,0 +1,108 @@
+//! # Blend modes
+//!
+//! How the premultiplied colour of a path is combined with what is already on the render target.
+//! Every mode is done by the fixed-function blending of the blit pass, so only the modes that can
+//! be written as `src * a + dst * b` are supported, and the blit pipeline has a variant for each.
+//!
+//! As the blit pass covers the whole target, with the coverage of the path multiplied into its
+//! colour, the Porter-Duff operators that don't keep the destination where the source is
+//! transparent affect the area outside the path too, in the same way as
+//! `globalCompositeOperation` in the HTML canvas. For example `SourceIn` clears everything that
+//! the path doesn't cover.
+
+use wgpu::BlendFactor::{self, *};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// The path is drawn over the destination, which is Porter-Duff's source-over.
+    #[default]
+    Normal,
+    /// The colours are multiplied, which darkens. This is only exact where the destination is
+    /// opaque, as the colour of the path is lost where it's transparent.
+    Multiply,
+    /// The inverses of the colours are multiplied, which lightens.
+    Screen,
+    /// The colours are added, and saturate.
+    Add,
+    /// Nothing is left, anywhere on the target.
+    Clear,
+    /// Only the path is left, and the rest of the target is cleared.
+    Source,
+    /// Only the destination is left, so the path isn't drawn at all.
+    Destination,
+    /// The destination is drawn over the path.
+    DestinationOver,
+    /// The path is only drawn where the destination is, which is removed.
+    SourceIn,
+    /// The destination is only kept where the path is.
+    DestinationIn,
+    /// The path is only drawn where the destination isn't, which is removed.
+    SourceOut,
+    /// The destination is only kept where the path isn't.
+    DestinationOut,
+    /// The path is drawn over the destination, but only where the destination is.
+    SourceAtop,
+    /// The destination is drawn over the path, but only where the path is.
+    DestinationAtop,
+    /// The parts of the path and the destination that don't overlap.
+    Xor,
+}
+
+impl BlendMode {
+    /// The factors that the source and destination are multiplied by, for the colour and the
+    /// alpha channels. The source is premultiplied.
+    pub(crate) fn factors(self) -> [(BlendFactor, BlendFactor); 2] {
+        let porter_duff = |src, dst| [(src, dst), (src, dst)];
+
+        match self {
+            Self::Normal => porter_duff(One, OneMinusSrcAlpha),
+            Self::Multiply => [(DstColor, OneMinusSrcAlpha), (One, OneMinusSrcAlpha)],
+            Self::Screen => [(One, OneMinusSrcColor), (One, OneMinusSrcAlpha)],
+            Self::Add => porter_duff(One, One),
+            Self::Clear => porter_duff(Zero, Zero),
+            Self::Source => porter_duff(One, Zero),
+            Self::Destination => porter_duff(Zero, One),
+            Self::DestinationOver => porter_duff(OneMinusDstAlpha, One),
+            Self::SourceIn => porter_duff(DstAlpha, Zero),
+            Self::DestinationIn => porter_duff(Zero, SrcAlpha),
+            Self::SourceOut => porter_duff(OneMinusDstAlpha, Zero),
+            Self::DestinationOut => porter_duff(Zero, OneMinusSrcAlpha),
+            Self::SourceAtop => porter_duff(DstAlpha, OneMinusSrcAlpha),
+            Self::DestinationAtop => porter_duff(OneMinusDstAlpha, SrcAlpha),
+            Self::Xor => porter_duff(OneMinusDstAlpha, OneMinusSrcAlpha),
+        }
+    }
+
+    /// Whether the target is left as it is wherever the path doesn't cover it. If not, the path
+    /// has to be drawn even when it's off-screen.
+    pub fn is_bounded(self) -> bool {
+        self.factors()
+            .iter()
+            .all(|&(_, dst)| matches!(dst, One | OneMinusSrcAlpha | OneMinusSrcColor))
+    }
+
+    /// Blends the premultiplied `src` onto `dst` in the same way as the blit pipeline.
+    pub(crate) fn blend(self, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        let [color, alpha] = self.factors();
+        let factor = |factor: BlendFactor, i: usize| match factor {
+            Zero => 0.,
+            One => 1.,
+            SrcColor => src[i],
+            OneMinusSrcColor => 1. - src[i],
+            SrcAlpha => src[3],
+            OneMinusSrcAlpha => 1. - src[3],
+            DstColor => dst[i],
+            OneMinusDstColor => 1. - dst[i],
+            DstAlpha => dst[3],
+            OneMinusDstAlpha => 1. - dst[3],
+            _ => unreachable!("{:?} is not used by any blend mode", factor),
+        };
+        let channel = |i: usize| {
+            let (src_factor, dst_factor) = if i == 3 { alpha } else { color };
+
+            (src[i] * factor(src_factor, i) + dst[i] * factor(dst_factor, i)).clamp(0., 1.)
+        };
+
+        [channel(0), channel(1), channel(2), channel(3)]
+    }
+}
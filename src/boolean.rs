@@ -0,0 +1,312 @@
+//! # Boolean operations
+//!
+//! Combines the filled areas of two paths. Every segment of both paths is split wherever it
+//! crosses any other segment, and each piece is kept if the result of the operation is inside on
+//! one side of it but not the other. The kept pieces are turned to have the inside on their left
+//! and chained back together into closed subpaths, which are filled with the non-zero rule.
+//!
+//! Open subpaths are closed with a straight line first, as they are when filled. Edges that the
+//! two paths share exactly are handled, but parts of curves that overlap without being identical
+//! are not split where they start to overlap, so may leave slivers.
+
+use crate::{
+    bounds::Bounds,
+    intersect,
+    path::{Path, PathBuilder, Segment},
+    scene::FillRule,
+};
+use cgmath::{InnerSpace, Vector2};
+
+/// Pieces shorter than this in `t` are not split off, as they are from crossings at the ends of
+/// segments where they meet their neighbours.
+const MIN_SPLIT: f32 = 1e-4;
+
+/// How far either side of a piece its inside and outside are sampled, relative to the size of
+/// the two paths.
+const SAMPLE_DISTANCE: f32 = 1e-4;
+
+/// How far apart the end of one piece and the start of the next can be for them to be chained
+/// together, relative to the size of the two paths.
+const CHAIN_TOLERANCE: f32 = 1e-3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Inside either path.
+    Union,
+    /// Inside both paths.
+    Intersection,
+    /// Inside the first path but not the second.
+    Difference,
+    /// Inside exactly one of the paths.
+    Xor,
+}
+
+impl Operation {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+            Self::Xor => a != b,
+        }
+    }
+}
+
+/// The segments of `path`, with a line closing each open subpath.
+fn closed_segments(path: &Path) -> Vec<Segment> {
+    path.subpaths()
+        .iter()
+        .flat_map(|subpath| {
+            let end = subpath.segments().last().unwrap().end();
+            let closing =
+                Some(Segment::Line(end, subpath.start())).filter(|_| end != subpath.start());
+
+            subpath.segments().iter().copied().chain(closing)
+        })
+        .collect()
+}
+
+/// The size of the box containing the control points of `segments`, which must not be empty.
+fn size(segments: &[Segment]) -> f32 {
+    let points = segments.iter().flat_map(|segment| match *segment {
+        Segment::Line(start, end) => vec![start, end],
+        Segment::Quadratic((start, control, end)) => vec![start, control, end],
+    });
+    let size = Bounds::from_points(points).unwrap().size();
+
+    size.x.max(size.y)
+}
+
+/// Combines the areas covered by filling `a` and `b` with `rule`. The result should be filled
+/// with `FillRule::NonZero`, and is empty if the paths are too big to be combined in `f32`.
+pub fn boolean(a: &Path, b: &Path, operation: Operation, rule: FillRule) -> Path {
+    let a_segments = closed_segments(a);
+    let b_segments = closed_segments(b);
+    let segments = a_segments
+        .iter()
+        .chain(&b_segments)
+        .copied()
+        .collect::<Vec<_>>();
+
+    if segments.is_empty() {
+        return Path::default();
+    }
+
+    let size = size(&segments);
+
+    // Where each segment is split, in `t`
+    let mut splits = vec![Vec::new(); segments.len()];
+
+    for i in 0..segments.len() {
+        for j in i + 1..segments.len() {
+            for (s, t) in intersect::segments(&segments[i], &segments[j]) {
+                splits[i].push(s);
+                splits[j].push(t);
+            }
+        }
+    }
+
+    let inside = |point: Vector2<f32>| {
+        operation.apply(
+            rule.is_inside(intersect::winding(a, point)),
+            rule.is_inside(intersect::winding(b, point)),
+        )
+    };
+
+    let mut pieces = Vec::new();
+
+    for (segment, mut splits) in segments.iter().zip(splits) {
+        splits.retain(|&t| t > MIN_SPLIT && t < 1. - MIN_SPLIT);
+        splits.sort_by(f32::total_cmp);
+        splits.dedup_by(|a, b| *a - *b < MIN_SPLIT);
+
+        let bounds = std::iter::once(0.)
+            .chain(splits)
+            .chain(std::iter::once(1.))
+            .collect::<Vec<_>>();
+
+        for range in bounds.windows(2) {
+            let piece = segment.slice(range[0], range[1]);
+            let tangent = piece.derivative(0.5);
+
+            if tangent.magnitude2() == 0. {
+                continue;
+            }
+
+            let normal = Vector2::new(-tangent.y, tangent.x).normalize() * size * SAMPLE_DISTANCE;
+            let middle = piece.point(0.5);
+
+            match (inside(middle + normal), inside(middle - normal)) {
+                (true, false) => pieces.push(piece),
+                (false, true) => pieces.push(piece.reversed()),
+                _ => {}
+            }
+        }
+    }
+
+    chain(pieces, size * CHAIN_TOLERANCE)
+}
+
+/// Joins `pieces` end to start into closed subpaths.
+fn chain(mut pieces: Vec<Segment>, tolerance: f32) -> Path {
+    let mut builder = PathBuilder::new();
+
+    while let Some(first) = pieces.pop() {
+        builder.move_to(first.start()).segment_to(first);
+
+        let mut end = first.end();
+
+        while (end - first.start()).magnitude() > tolerance {
+            let next = pieces
+                .iter()
+                .enumerate()
+                .map(|(i, piece)| (i, (piece.start() - end).magnitude()))
+                .filter(|&(_, distance)| distance <= tolerance)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(i, _)| i);
+
+            match next {
+                Some(i) => {
+                    let piece = pieces.swap_remove(i);
+
+                    builder.segment_to(piece);
+                    end = piece.end();
+                }
+                // The pieces don't make a closed loop, which can happen when curves overlap
+                None => break,
+            }
+        }
+
+        builder.close();
+    }
+
+    // Paths with huge coordinates can overflow when they are split, in which case there is no
+    // result
+    builder.build().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Overlapping squares, from (0, 0) to (2, 2) and from (1, 1) to (3, 3).
+    fn squares() -> (Path, Path) {
+        let square = |origin: f32| {
+            let mut builder = PathBuilder::new();
+            builder.rect(Vector2::new(origin, origin), Vector2::new(2., 2.));
+
+            builder.build().unwrap()
+        };
+
+        (square(0.), square(1.))
+    }
+
+    /// Whether the points in only the first square, in both and in only the second are inside
+    /// the result.
+    fn regions(operation: Operation) -> [bool; 3] {
+        let (a, b) = squares();
+        let out = boolean(&a, &b, operation, FillRule::NonZero);
+        let inside =
+            |x, y| FillRule::NonZero.is_inside(intersect::winding(&out, Vector2::new(x, y)));
+
+        assert!(!inside(-1., -1.) && !inside(2.5, 0.5) && !inside(0.5, 2.5));
+
+        [inside(0.5, 0.5), inside(1.5, 1.5), inside(2.5, 2.5)]
+    }
+
+    /// The corners of every segment of `path`, sorted.
+    fn corners(path: &Path) -> Vec<[i32; 2]> {
+        let mut corners = path
+            .segments()
+            .map(|segment| {
+                let start = segment.start();
+
+                [start.x.round() as i32, start.y.round() as i32]
+            })
+            .collect::<Vec<_>>();
+        corners.sort_unstable();
+
+        corners
+    }
+
+    #[test]
+    fn operations() {
+        assert_eq!(regions(Operation::Union), [true, true, true]);
+        assert_eq!(regions(Operation::Intersection), [false, true, false]);
+        assert_eq!(regions(Operation::Difference), [true, false, false]);
+        assert_eq!(regions(Operation::Xor), [true, false, true]);
+    }
+
+    #[test]
+    fn split_at_crossings() {
+        let (a, b) = squares();
+        let intersection = boolean(&a, &b, Operation::Intersection, FillRule::NonZero);
+
+        assert_eq!(intersection.subpaths().len(), 1);
+        assert_eq!(corners(&intersection), vec![[1, 1], [1, 2], [2, 1], [2, 2]]);
+    }
+
+    #[test]
+    fn curves() {
+        // Two unit circles whose centres are 1 apart meet at `x` = 0.5
+        let circle = |x| {
+            let mut builder = PathBuilder::new();
+            builder.circle(Vector2::new(x, 0.), 1.);
+
+            builder.build().unwrap()
+        };
+        let lens = boolean(
+            &circle(0.),
+            &circle(1.),
+            Operation::Intersection,
+            FillRule::NonZero,
+        );
+        let bounds = lens.bounds().unwrap();
+        let half_height = 0.75f32.sqrt();
+
+        assert!(bounds.min.x.abs() < 1e-3 && (bounds.max.x - 1.).abs() < 1e-3);
+        assert!((bounds.max.y - half_height).abs() < 1e-3);
+        assert!((bounds.min.y + half_height).abs() < 1e-3);
+    }
+
+    #[test]
+    fn empty() {
+        let (a, _) = squares();
+
+        assert!(boolean(
+            &a,
+            &Path::default(),
+            Operation::Intersection,
+            FillRule::NonZero
+        )
+        .is_empty());
+        assert_eq!(
+            intersect::winding(
+                &boolean(&a, &Path::default(), Operation::Union, FillRule::NonZero),
+                Vector2::new(1., 1.)
+            )
+            .abs(),
+            1
+        );
+    }
+
+    #[test]
+    fn huge() {
+        // The size of these is infinite in `f32`, so the pieces can't be sampled or chained
+        let square = |origin: f32| {
+            let mut builder = PathBuilder::new();
+            builder.rect(Vector2::new(origin, origin), Vector2::new(3e38, 3e38));
+
+            builder.build().unwrap()
+        };
+
+        for &operation in &[Operation::Union, Operation::Intersection] {
+            boolean(&square(-3e38), &square(-1e38), operation, FillRule::NonZero);
+        }
+
+        // A piece that overflowed leaves nothing to fill
+        let overflowed = Segment::Line(Vector2::new(f32::INFINITY, 0.), Vector2::new(0., 0.));
+
+        assert!(chain(vec![overflowed], 1.).is_empty());
+    }
+}
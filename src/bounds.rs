@@ -0,0 +1,86 @@
+//! # Bounds
+//!
+//! Axis-aligned bounding boxes. The bounds of a segment are exact rather than the box around its
+//! control points: a quadratic reaches furthest in x or y either at an end or where its
+//! derivative in that axis is zero, which happens at most once per axis.
+
+use cgmath::Vector2;
+
+/// An axis-aligned box, including its edges.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Bounds {
+    /// The box containing just `point`.
+    pub fn from_point(point: Vector2<f32>) -> Self {
+        Self {
+            min: point,
+            max: point,
+        }
+    }
+
+    /// The smallest box containing all of `points`, or `None` if there aren't any.
+    pub fn from_points(points: impl IntoIterator<Item = Vector2<f32>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = Self::from_point(points.next()?);
+
+        Some(points.fold(first, |bounds, point| bounds.including(point)))
+    }
+
+    /// The box with its corners at `[x, y]` and `[x + width, y + height]`.
+    pub fn from_rect([x, y, width, height]: [f32; 4]) -> Self {
+        Self {
+            min: Vector2::new(x, y),
+            max: Vector2::new(x + width, y + height),
+        }
+    }
+
+    /// The smallest box containing both this box and `point`.
+    pub fn including(self, point: Vector2<f32>) -> Self {
+        Self {
+            min: Vector2::new(self.min.x.min(point.x), self.min.y.min(point.y)),
+            max: Vector2::new(self.max.x.max(point.x), self.max.y.max(point.y)),
+        }
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(self, other: Self) -> Self {
+        self.including(other.min).including(other.max)
+    }
+
+    /// The box grown by `amount` on every side.
+    pub fn expanded(self, amount: f32) -> Self {
+        let amount = Vector2::new(amount, amount);
+
+        Self {
+            min: self.min - amount,
+            max: self.max + amount,
+        }
+    }
+
+    pub fn size(&self) -> Vector2<f32> {
+        self.max - self.min
+    }
+
+    pub fn centre(&self) -> Vector2<f32> {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        self.min.x <= point.x
+            && point.x <= self.max.x
+            && self.min.y <= point.y
+            && point.y <= self.max.y
+    }
+
+    /// Whether the boxes overlap, including if they only touch.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
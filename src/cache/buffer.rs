@@ -18,7 +18,7 @@ impl<T> Deref for AlignedBufferCache<T> {
     type Target = Option<wgpu::Buffer>;
 
     fn deref(&self) -> &Self::Target {
-        &*self.inner
+        &self.inner
     }
 }
 
@@ -58,11 +58,10 @@ where
         let out = self
             .inner
             .append(bytemuck::cast_slice(&vals[..]).iter().copied());
-        self.inner.append(
-            std::iter::repeat(0).take(
-                self.alignment as usize - (self.inner.len() % self.alignment as u64) as usize,
-            ),
-        );
+        self.inner.append(std::iter::repeat_n(
+            0,
+            self.alignment as usize - (self.inner.len() % self.alignment as u64) as usize,
+        ));
 
         out
     }
@@ -89,6 +88,10 @@ impl<T> BufferCache<T> {
         self.unwritten.len() as u64 + self.buffer_len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn append_many<
         Int: TryFrom<u64>,
         O: FromIterator<Result<Range<Int>, <Int as TryFrom<u64>>::Error>>,
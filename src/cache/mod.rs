@@ -1,6 +1,8 @@
 mod buffer;
+mod texture;
 
 pub use buffer::{AlignedBufferCache, BufferCache};
+pub use texture::{Rect, TextureCache};
 
 pub trait CacheCommon {
     type Key;
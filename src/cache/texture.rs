@@ -0,0 +1,191 @@
+use crate::cache::{Cache, CacheCommon};
+use std::{mem, ops::Deref};
+use wgpu::util::DeviceExt;
+
+/// Packs images into a single texture, in rows of images left to right. Like `BufferCache`,
+/// everything is appended again after each `clear`, but the texture itself is kept and only
+/// replaced when it's too small.
+pub struct TextureCache<T> {
+    format: wgpu::TextureFormat,
+    /// Everything appended since the last `clear`, with where it is in the texture.
+    entries: Vec<(Rect, Vec<T>)>,
+    /// How many of `entries` are in the texture.
+    written: usize,
+    texture: wgpu::Texture,
+    size: (u32, u32),
+    /// The width that rows are packed into, which grows to fit the widest image.
+    row_width: u32,
+    /// Where the next image goes, along the top of the current row.
+    cursor: (u32, u32),
+    /// The height of the tallest image in the current row.
+    row_height: u32,
+}
+
+/// The region of the texture that an image was packed into, as `[x, y, width, height]`.
+pub type Rect = [u32; 4];
+
+/// The narrowest that rows are packed, so that small images don't each start a new row.
+const MIN_ROW_WIDTH: u32 = 1024;
+
+fn create_texture(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    (width, height): (u32, u32),
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("texture_cache"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+    })
+}
+
+impl<T> TextureCache<T> {
+    /// Unlike `BufferCache`, the texture is created straight away, as it has to be bound even if
+    /// nothing is drawn from it.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self {
+            format,
+            entries: Vec::new(),
+            written: 0,
+            texture: create_texture(device, format, (1, 1)),
+            size: (1, 1),
+            row_width: MIN_ROW_WIDTH,
+            cursor: (0, 0),
+            row_height: 0,
+        }
+    }
+
+    /// The size of the texture, which changes whenever it's replaced.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// The size that the texture needs to be to hold everything that has been appended.
+    fn extent(&self) -> (u32, u32) {
+        self.entries
+            .iter()
+            .fold((0, 0), |(width, height), ([x, y, w, h], _)| {
+                (width.max(x + w), height.max(y + h))
+            })
+    }
+}
+
+impl<T> Deref for TextureCache<T> {
+    type Target = wgpu::Texture;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture
+    }
+}
+
+impl<T> CacheCommon for TextureCache<T>
+where
+    T: bytemuck::Pod,
+{
+    type Key = Rect;
+
+    fn update(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let (width, height) = self.extent();
+
+        if width > self.size.0 || height > self.size.1 {
+            self.size = (
+                width.next_power_of_two().max(self.size.0),
+                height.next_power_of_two().max(self.size.1),
+            );
+            self.texture = create_texture(device, self.format, self.size);
+            self.written = 0;
+        }
+
+        for ([x, y, width, height], texels) in &self.entries[self.written..] {
+            let unpadded_bytes_per_row = width * mem::size_of::<T>() as u32;
+            let bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+                * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+            let mut contents = vec![0; (bytes_per_row * height) as usize];
+
+            for (row, texels) in contents
+                .chunks_exact_mut(bytes_per_row as usize)
+                .zip(bytemuck::cast_slice(texels).chunks_exact(unpadded_bytes_per_row as usize))
+            {
+                row[..texels.len()].copy_from_slice(texels);
+            }
+
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &contents,
+                usage: wgpu::BufferUsage::COPY_SRC,
+            });
+
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: &buffer,
+                    layout: wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row,
+                        rows_per_image: *height,
+                    },
+                },
+                wgpu::TextureCopyView {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: *x, y: *y, z: 0 },
+                },
+                wgpu::Extent3d {
+                    width: *width,
+                    height: *height,
+                    depth: 1,
+                },
+            );
+        }
+
+        self.written = self.entries.len();
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.written = 0;
+        self.row_width = MIN_ROW_WIDTH;
+        self.cursor = (0, 0);
+        self.row_height = 0;
+    }
+}
+
+impl<T, I> Cache<((u32, u32), I)> for TextureCache<T>
+where
+    I: IntoIterator<Item = T>,
+    T: bytemuck::Pod,
+{
+    /// Appends an image of the given size, whose texels are row by row from the top left.
+    fn append(&mut self, ((width, height), texels): ((u32, u32), I)) -> Self::Key {
+        let texels: Vec<T> = texels.into_iter().collect();
+
+        assert_eq!(texels.len(), width as usize * height as usize);
+
+        if width == 0 || height == 0 {
+            return [0, 0, 0, 0];
+        }
+
+        self.row_width = self.row_width.max(width);
+
+        if self.cursor.0 + width > self.row_width {
+            self.cursor = (0, self.cursor.1 + self.row_height);
+            self.row_height = 0;
+        }
+
+        let rect = [self.cursor.0, self.cursor.1, width, height];
+
+        self.cursor.0 += width;
+        self.row_height = self.row_height.max(height);
+        self.entries.push((rect, texels));
+
+        rect
+    }
+}
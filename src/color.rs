@@ -0,0 +1,85 @@
+//! # Colour management
+//!
+//! Colours are given as straight (non-premultiplied) sRGB, as in SVG and CSS, and everything
+//! after that is premultiplied: the colours passed to the blit pass, the blending onto the render
+//! target and the clear colour. `ColorSpace` decides whether that happens in linear light or on
+//! the sRGB values themselves.
+
+use crate::scene::Color;
+
+/// How colours are stored in the render target, which decides the space they are blended in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The target is an sRGB texture. Colours are converted to linear light, blended there and
+    /// converted back when they're stored, so edges and translucent paths have the right
+    /// brightness.
+    #[default]
+    Srgb,
+    /// The target is a plain texture and colours are written as they are, so the
+    /// gamma-encoded sRGB values are blended directly. This is wrong but is what most browsers
+    /// do.
+    Gamma,
+}
+
+impl ColorSpace {
+    /// The format of the render target, which the swap chain must be created with.
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Srgb => wgpu::TextureFormat::Bgra8UnormSrgb,
+            Self::Gamma => wgpu::TextureFormat::Bgra8Unorm,
+        }
+    }
+
+    /// Converts a straight sRGB colour to the premultiplied colour that is blended.
+    pub fn premultiply(self, [r, g, b, a]: Color) -> Color {
+        let channel = |c: f32| self.decode(c) * a;
+
+        [channel(r), channel(g), channel(b), a]
+    }
+
+    /// `premultiply` as the value that a render pass clears to.
+    pub fn clear_color(self, color: Color) -> wgpu::Color {
+        let [r, g, b, a] = self.premultiply(color);
+
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        }
+    }
+
+    /// Converts a colour channel as it is stored in the target to the value that is blended.
+    pub(crate) fn decode(self, c: f32) -> f32 {
+        match self {
+            Self::Srgb if c <= 0.04045 => c / 12.92,
+            Self::Srgb => ((c + 0.055) / 1.055).powf(2.4),
+            Self::Gamma => c,
+        }
+    }
+
+    /// The inverse of `decode`.
+    pub(crate) fn encode(self, c: f32) -> f32 {
+        match self {
+            Self::Srgb if c <= 0.003_130_8 => c * 12.92,
+            Self::Srgb => 1.055 * c.powf(1. / 2.4) - 0.055,
+            Self::Gamma => c,
+        }
+    }
+
+    /// Converts a premultiplied pixel read back from the target to straight alpha. The alpha of
+    /// an sRGB target is always linear, so the colour has to be decoded before it's divided.
+    pub(crate) fn unpremultiply(self, [r, g, b, a]: [u8; 4]) -> [u8; 4] {
+        let channel = |c: u8| match (self, a) {
+            (_, 0) => 0,
+            (Self::Gamma, a) => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+            (Self::Srgb, a) => {
+                let straight = self.decode(c as f32 / 255.) / (a as f32 / 255.);
+
+                (self.encode(straight.min(1.)) * 255.).round() as u8
+            }
+        };
+
+        [channel(r), channel(g), channel(b), a]
+    }
+}
@@ -0,0 +1,224 @@
+//! # Dashes
+//!
+//! Splits a path into dashes by arc length, following SVG's `stroke-dasharray` and
+//! `stroke-dashoffset`. Each dash becomes an open subpath of its own, so that it has ends of its
+//! own, and a dash that spans several segments stays in one subpath so that it continues
+//! smoothly across the joins between them. The pattern starts again at the start of each subpath.
+//! On a closed subpath, a dash that runs over the end and the start is one dash, as in SVG.
+//!
+//! `Canvas` strokes without caps, so its dashes always have butt ends. For round or square caps
+//! on each dash, the dashed path can be passed to `outline::outline` with that `Cap` and filled.
+
+use crate::{
+    measure::SegmentLength,
+    path::{Path, PathBuilder, Segment},
+};
+
+/// Splits `path` into the dashes given by `pattern`, which alternates between the lengths of
+/// dashes and the gaps between them, starting `offset` into the pattern. As in SVG, a pattern
+/// with an odd number of lengths is repeated to make it even, and if the pattern is empty, has
+/// any negative or non-finite lengths or only has lengths of zero the path is returned
+/// unchanged.
+pub fn dash(path: &Path, pattern: &[f32], offset: f32) -> Path {
+    let total = pattern.iter().sum::<f32>();
+
+    if total <= 0. || !total.is_finite() || pattern.iter().any(|&l| l < 0.) {
+        return path.clone();
+    }
+
+    let pattern = if pattern.len() % 2 == 1 {
+        pattern.repeat(2)
+    } else {
+        pattern.to_vec()
+    };
+
+    // Find where in the pattern the offset lands
+    let mut start = (0, pattern[0]);
+    let mut skip = if offset.is_finite() {
+        offset.rem_euclid(total)
+    } else {
+        0.
+    };
+
+    while skip > 0. && skip >= start.1 {
+        skip -= start.1;
+        start.0 = (start.0 + 1) % pattern.len();
+        start.1 = pattern[start.0];
+    }
+    start.1 -= skip;
+
+    let mut builder = PathBuilder::new();
+
+    for subpath in path.subpaths() {
+        let (mut index, mut remaining) = start;
+        // The pieces of each dash in the subpath, where the last one is still being added to
+        // while `in_dash` is set
+        let mut dashes: Vec<Vec<Segment>> = Vec::new();
+        let mut in_dash = false;
+
+        for &segment in subpath.segments() {
+            let measured = SegmentLength::new(segment);
+            let length = measured.length();
+            let mut position = 0.;
+
+            loop {
+                let step = remaining.min(length - position);
+
+                // Gaps only need to be skipped over, as are dashes of zero length
+                if index % 2 == 0 && step > 0. {
+                    let piece =
+                        segment.slice(measured.t_at(position), measured.t_at(position + step));
+
+                    if !in_dash {
+                        dashes.push(Vec::new());
+                        in_dash = true;
+                    }
+
+                    dashes.last_mut().unwrap().push(piece);
+                }
+
+                position += step;
+                remaining -= step;
+
+                if remaining > 0. {
+                    break;
+                }
+
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+                in_dash = false;
+            }
+        }
+
+        // The first dash starts at the start of the subpath if the pattern starts in a dash, so
+        // on a closed subpath it carries on from the last one if that reaches the end
+        let starts_in_dash = start.0 % 2 == 0 && start.1 > 0.;
+
+        if subpath.is_closed() && starts_in_dash && in_dash && dashes.len() > 1 {
+            let first = dashes.remove(0);
+
+            dashes.last_mut().unwrap().extend(first);
+        }
+
+        for pieces in dashes {
+            builder.move_to(pieces[0].start());
+
+            for piece in pieces {
+                builder.segment_to(piece);
+            }
+        }
+    }
+
+    // Every point comes from a segment of `path`, which is already valid
+    builder.build().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        outline::{self, Cap, StrokeStyle},
+        renderer::Options,
+    };
+    use cgmath::Vector2;
+
+    /// A square with sides of 4, starting at the origin.
+    fn square(closed: bool) -> Path {
+        let mut builder = Path::builder();
+
+        builder
+            .move_to(Vector2::new(0., 0.))
+            .line_to(Vector2::new(4., 0.))
+            .line_to(Vector2::new(4., 4.))
+            .line_to(Vector2::new(0., 4.));
+
+        if closed {
+            builder.close();
+        } else {
+            builder.line_to(Vector2::new(0., 0.));
+        }
+
+        builder.build().unwrap()
+    }
+
+    fn ends(path: &Path) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+        path.subpaths()
+            .iter()
+            .map(|subpath| {
+                let segments = subpath.segments();
+
+                (segments[0].start(), segments[segments.len() - 1].end())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dashes() {
+        let dashed = dash(&square(false), &[2., 1.], 0.5);
+        let ends = ends(&dashed);
+
+        // The dashes run from 0 to 1.5, 2.5 to 4.5 and so on, so the last one is cut off by the
+        // end and the second turns the first corner
+        assert_eq!(ends.len(), 6);
+        assert_eq!(ends[0], (Vector2::new(0., 0.), Vector2::new(1.5, 0.)));
+        assert_eq!(ends[1], (Vector2::new(2.5, 0.), Vector2::new(4., 0.5)));
+        assert_eq!(dashed.subpaths()[1].segments().len(), 2);
+        assert_eq!(ends[5], (Vector2::new(0., 1.5), Vector2::new(0., 0.)));
+    }
+
+    #[test]
+    fn closed_dashes_wrap() {
+        let dashed = dash(&square(true), &[2., 1.], 0.5);
+        let ends = ends(&dashed);
+
+        // The last dash carries on over the start into the first
+        assert_eq!(ends.len(), 5);
+        assert_eq!(ends[0], (Vector2::new(2.5, 0.), Vector2::new(4., 0.5)));
+        assert_eq!(ends[4], (Vector2::new(0., 1.5), Vector2::new(1.5, 0.)));
+        assert!(dashed.subpaths().iter().all(|subpath| !subpath.is_closed()));
+
+        // Starting in a gap, there is nothing to join up
+        assert_eq!(dash(&square(true), &[2., 1.], 2.5).subpaths().len(), 6);
+    }
+
+    #[test]
+    fn outlined_caps() {
+        let line = Path::from_polyline(vec![[0., 0.], [10., 0.]]).unwrap();
+        let dashed = dash(&line, &[2., 2.], 0.);
+        let outlined = |cap| {
+            let style = StrokeStyle {
+                width: 1.,
+                cap,
+                ..StrokeStyle::default()
+            };
+
+            outline::outline(&dashed, &style, &Options::default())
+        };
+
+        assert_eq!(dashed.subpaths().len(), 3);
+
+        // Each dash gets both of its caps. Round caps are approximated by quadratics, which
+        // bulge slightly past the circle.
+        for (cap, reach) in [(Cap::Butt, 0.), (Cap::Square, 0.5), (Cap::Round, 0.5)] {
+            let outline = outlined(cap);
+            let bounds = outline.bounds().unwrap();
+
+            assert_eq!(outline.subpaths().len(), 3);
+            assert!((bounds.min.x + reach).abs() < 1e-3, "{:?}", cap);
+            assert!((bounds.max.x - 10. - reach).abs() < 1e-3, "{:?}", cap);
+
+            for subpath in outline.subpaths() {
+                let (min, max) = subpath.segments().iter().fold(
+                    (f32::INFINITY, f32::NEG_INFINITY),
+                    |(min, max), segment| {
+                        let bounds = segment.bounds();
+
+                        (min.min(bounds.min.x), max.max(bounds.max.x))
+                    },
+                );
+
+                assert!((max - min - 2. - 2. * reach).abs() < 2e-3, "{:?}", cap);
+            }
+        }
+    }
+}
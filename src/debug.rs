@@ -0,0 +1,118 @@
+//! # Debug output
+//!
+//! Writes out what the tessellator produced for a stroke as an SVG, so that the effect of changing
+//! `Options::num_segments` and `Options::spacing` can be inspected and diffed without a GPU. The
+//! output contains, from bottom to top:
+//!
+//! - `quads`: the triangles drawn with `sign == 0`
+//! - `hulls`: the control polygons of the offset quadratics
+//! - `outer`/`inner`: the offset quadratics drawn with `sign == 1` and `sign == -1` respectively
+//! - `curve`: the original path
+//!
+//! Straight lines in the path only produce quads.
+
+use crate::{
+    path::{Path, Segment},
+    renderer::{Options, STROKE_WIDTH},
+    tessellate::{self, StrokeSegment},
+};
+use cgmath::Vector2;
+use std::io::{self, Write};
+
+/// The width of the lines in the output, in screen coordinates.
+const LINE_WIDTH: f32 = 0.003;
+
+/// Writes the decomposition of the stroke that `Canvas::render` would draw for `path`.
+pub fn write_stroke_svg(mut out: impl Write, path: &Path, options: &Options) -> io::Result<()> {
+    let segments =
+        tessellate::decompose_stroke(path, STROKE_WIDTH, options.num_segments, options.spacing)
+            .collect::<Vec<_>>();
+
+    // The `(outer, inner)` curves of the segments that have them
+    let offsets = segments
+        .iter()
+        .filter_map(|segment| match *segment {
+            StrokeSegment::Line { .. } => None,
+            StrokeSegment::Curve { outer, inner, .. } => Some((outer, inner)),
+        })
+        .collect::<Vec<_>>();
+
+    let point = |p: Vector2<f32>| format!("{},{}", p.x, p.y);
+    let path_data = |segment: Segment| match segment {
+        Segment::Line(start, end) => format!("M{} L{}", point(start), point(end)),
+        Segment::Quadratic((start, control, end)) => {
+            format!("M{} Q{} {}", point(start), point(control), point(end))
+        }
+    };
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -1 2 2" width="800" height="800">"#
+    )?;
+    // Screen coordinates have the y axis pointing up
+    writeln!(
+        out,
+        r#"<g transform="scale(1,-1)" fill="none" stroke-width="{}">"#,
+        LINE_WIDTH
+    )?;
+
+    writeln!(
+        out,
+        r##"<g id="quads" fill="#00c000" fill-opacity="0.25" stroke="#00c000">"##
+    )?;
+    for triangle in segments.iter().flat_map(|segment| segment.quad_triangles()) {
+        let points = triangle.iter().map(|p| point(*p)).collect::<Vec<_>>();
+        writeln!(out, r#"<polygon points="{}"/>"#, points.join(" "))?;
+    }
+    writeln!(out, "</g>")?;
+
+    writeln!(
+        out,
+        r##"<g id="hulls" stroke="#808080" stroke-dasharray="{}">"##,
+        LINE_WIDTH * 3.
+    )?;
+    for (start, control, end) in offsets
+        .iter()
+        .flat_map(|&(outer, inner)| vec![outer, inner])
+    {
+        writeln!(
+            out,
+            r#"<polyline points="{} {} {}"/>"#,
+            point(start),
+            point(control),
+            point(end)
+        )?;
+    }
+    writeln!(out, "</g>")?;
+
+    for (id, color, curves) in [
+        (
+            "outer",
+            "#0000ff",
+            offsets
+                .iter()
+                .map(|&(outer, _)| Segment::Quadratic(outer))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "inner",
+            "#ff0000",
+            offsets
+                .iter()
+                .map(|&(_, inner)| Segment::Quadratic(inner))
+                .collect::<Vec<_>>(),
+        ),
+        ("curve", "#000000", path.segments().collect::<Vec<_>>()),
+    ]
+    .iter()
+    {
+        writeln!(out, r#"<g id="{}" stroke="{}">"#, id, color)?;
+        for curve in curves {
+            writeln!(out, r#"<path d="{}"/>"#, path_data(*curve))?;
+        }
+        writeln!(out, "</g>")?;
+    }
+
+    writeln!(out, "</g>")?;
+    writeln!(out, "</svg>")
+}
@@ -0,0 +1,284 @@
+//! # Headless rendering
+//!
+//! Renders into a texture that we own instead of a swap chain, and reads the result back to the
+//! CPU. No window or surface is needed, so this works on servers and with software adapters
+//! such as lavapipe or SwiftShader.
+
+use crate::{
+    color::ColorSpace,
+    path::Path,
+    renderer::{Canvas, InvalidTessellation, Options},
+    scene::Scene,
+};
+use std::{fmt, fs, io};
+
+#[derive(Debug)]
+pub enum Error {
+    /// No adapter is available, not even a software one.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// Mapping the buffer that the rendered image is copied into failed.
+    ReadBack(wgpu::BufferAsyncError),
+    /// `Options::validate` found an invalid vertex.
+    Tessellation(InvalidTessellation),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAdapter => write!(f, "No suitable adapter found"),
+            Self::RequestDevice(e) => write!(f, "Could not create device: {}", e),
+            Self::ReadBack(e) => write!(f, "Could not read back rendered image: {}", e),
+            Self::Tessellation(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(e)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for Error {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        Self::ReadBack(e)
+    }
+}
+
+impl From<InvalidTessellation> for Error {
+    fn from(e: InvalidTessellation) -> Self {
+        Self::Tessellation(e)
+    }
+}
+
+/// An 8-bit straight (non-premultiplied) RGBA image, stored row-by-row from the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Creates an image from premultiplied pixels, as they are stored in a target with the given
+    /// colour space.
+    pub(crate) fn from_premultiplied(
+        width: u32,
+        height: u32,
+        pixels: impl IntoIterator<Item = [u8; 4]>,
+        color_space: ColorSpace,
+    ) -> Self {
+        let data = pixels
+            .into_iter()
+            .flat_map(|pixel| color_space.unpremultiply(pixel).to_vec())
+            .collect();
+
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    pub fn write_png(&self, out: impl io::Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder.write_header()?.write_image_data(&self.data)
+    }
+
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), png::EncodingError> {
+        self.write_png(io::BufWriter::new(fs::File::create(path)?))
+    }
+}
+
+pub struct Headless {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    canvas: Canvas,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    /// The colour space that `target` was created for.
+    color_space: ColorSpace,
+    size: (u32, u32),
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    (width, height): (u32, u32),
+    color_space: ColorSpace,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("headless_target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: color_space.format(),
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+    });
+    let target_view = target.create_view(&Default::default());
+
+    (target, target_view)
+}
+
+impl Headless {
+    /// Creates a device without a surface and a render target of the given size.
+    pub async fn new(size: (u32, u32)) -> Result<Self, Error> {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or(Error::NoAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::default(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: cfg!(debug_assertions),
+                },
+                None,
+            )
+            .await?;
+
+        let color_space = ColorSpace::default();
+        let (target, target_view) = create_target(&device, size, color_space);
+        let canvas = Canvas::new(&device, size);
+
+        Ok(Self {
+            device,
+            queue,
+            canvas,
+            target,
+            target_view,
+            color_space,
+            size,
+        })
+    }
+
+    /// Recreates the render target if `options` asks for a different colour space.
+    fn prepare(&mut self, options: &Options) {
+        if self.color_space != options.color_space {
+            let (target, target_view) = create_target(&self.device, self.size, options.color_space);
+
+            self.target = target;
+            self.target_view = target_view;
+            self.color_space = options.color_space;
+        }
+    }
+
+    /// The headless equivalent of `Canvas::render`.
+    pub fn render(&mut self, path: &Path, options: &Options) -> Result<RgbaImage, Error> {
+        self.prepare(options);
+
+        let commands = self
+            .canvas
+            .render(&self.device, &self.target_view, path, options)?;
+
+        self.read_back(commands)
+    }
+
+    /// The headless equivalent of `Canvas::render_scene`.
+    pub fn render_scene(&mut self, scene: &Scene, options: &Options) -> Result<RgbaImage, Error> {
+        self.prepare(options);
+
+        let commands = self
+            .canvas
+            .render_scene(&self.device, &self.target_view, scene, options)?;
+
+        self.read_back(commands)
+    }
+
+    fn read_back(&self, commands: Option<wgpu::CommandBuffer>) -> Result<RgbaImage, Error> {
+        const BYTES_PER_PIXEL: u32 = 4;
+
+        let (width, height) = self.size;
+        let unpadded_bytes_per_row = width * BYTES_PER_PIXEL;
+        let bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("headless_readback"),
+            size: (bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless_readback"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row,
+                    rows_per_image: height,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+
+        self.queue
+            .submit(commands.into_iter().chain(Some(encoder.finish())));
+
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping)?;
+
+        let is_bgra = matches!(
+            self.color_space.format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(bytes_per_row as usize)
+            .flat_map(|row| {
+                row[..unpadded_bytes_per_row as usize]
+                    .chunks(BYTES_PER_PIXEL as usize)
+                    .map(|pixel| {
+                        if is_bgra {
+                            [pixel[2], pixel[1], pixel[0], pixel[3]]
+                        } else {
+                            [pixel[0], pixel[1], pixel[2], pixel[3]]
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        buffer.unmap();
+
+        Ok(RgbaImage::from_premultiplied(
+            width,
+            height,
+            pixels,
+            self.color_space,
+        ))
+    }
+}
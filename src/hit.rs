@@ -0,0 +1,299 @@
+//! # Hit testing
+//!
+//! Finding what is under a point, for picking in an editor. Fills are tested with the winding
+//! number of the path around the point, and strokes by the distance to the nearest point on the
+//! path, both in the same space as the path.
+//!
+//! The nearest point on a quadratic is where the vector to it is perpendicular to the curve,
+//! which is a cubic in `t`. Rather than solving that in closed form, the curve is sampled to find
+//! the closest part and the result is refined with Newton's method.
+
+use crate::{
+    intersect,
+    path::{Path, Segment},
+    scene::{FillRule, Scene},
+};
+use cgmath::{InnerSpace, Vector2};
+
+/// The number of equal steps in `t` that a quadratic is sampled at to find the part closest to a
+/// point. The distance to a quadratic has at most two local minima, which this separates for all
+/// but the most extreme curves.
+const NEAREST_SAMPLES: usize = 16;
+
+const NEWTON_ITERATIONS: usize = 4;
+
+/// The closest point on a path to another point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Nearest {
+    /// The index of the segment in `Path::segments`.
+    pub index: usize,
+    pub segment: Segment,
+    pub t: f32,
+    pub point: Vector2<f32>,
+    pub distance: f32,
+}
+
+/// The `t` of the closest point on `segment` to `point`, which is meaningless if `point` is not
+/// finite.
+pub fn nearest_t(segment: &Segment, point: Vector2<f32>) -> f32 {
+    let distance2 = |t: f32| (segment.point(t) - point).magnitude2();
+
+    match *segment {
+        Segment::Line(start, end) => {
+            let direction = end - start;
+            let length2 = direction.magnitude2();
+
+            if length2 == 0. {
+                0.
+            } else {
+                ((point - start).dot(direction) / length2).clamp(0., 1.)
+            }
+        }
+        Segment::Quadratic((start, control, end)) => {
+            let mut t = (0..=NEAREST_SAMPLES)
+                .map(|i| i as f32 / NEAREST_SAMPLES as f32)
+                .min_by(|&a, &b| distance2(a).total_cmp(&distance2(b)))
+                .unwrap();
+
+            // Newton's method on `(point(t) - point) . derivative(t) == 0`, where the second
+            // derivative is constant
+            let second = 2. * (start - 2. * control + end);
+
+            for _ in 0..NEWTON_ITERATIONS {
+                let offset = segment.point(t) - point;
+                let derivative = segment.derivative(t);
+                let slope = derivative.magnitude2() + offset.dot(second);
+
+                if slope <= 0. {
+                    break;
+                }
+
+                t = (t - offset.dot(derivative) / slope).clamp(0., 1.);
+            }
+
+            t
+        }
+    }
+}
+
+/// The closest point on any segment of `path` to `point`, or `None` if the path is empty or
+/// `point` is not finite.
+pub fn nearest(path: &Path, point: Vector2<f32>) -> Option<Nearest> {
+    if !(point.x.is_finite() && point.y.is_finite()) {
+        return None;
+    }
+
+    path.segments()
+        .enumerate()
+        .map(|(index, segment)| {
+            let t = nearest_t(&segment, point);
+            let on_segment = segment.point(t);
+
+            Nearest {
+                index,
+                segment,
+                t,
+                point: on_segment,
+                distance: (on_segment - point).magnitude(),
+            }
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Whether `point` is inside the area covered by filling `path` with `rule`.
+pub fn fill_contains(path: &Path, point: Vector2<f32>, rule: FillRule) -> bool {
+    rule.is_inside(intersect::winding(path, point))
+}
+
+/// Whether `point` is within the stroke of `path` with the given full width.
+pub fn stroke_contains(path: &Path, point: Vector2<f32>, width: f32) -> bool {
+    nearest(path, point).is_some_and(|nearest| nearest.distance <= width / 2.)
+}
+
+/// The index in `Scene::paths` of the topmost path whose fill or stroke is under `point`, in
+/// user space. Strokes are tested after trimming and dashing. Only paths whose bounds in
+/// `Scene::index` contain `point` are tested, and nothing is under a point that is not finite.
+pub fn pick(scene: &Scene, point: Vector2<f32>) -> Option<usize> {
+    if !(point.x.is_finite() && point.y.is_finite()) {
+        return None;
+    }
+
+    scene
+        .index()
+        .query_point(point)
+        .into_iter()
+        .rev()
+        .find(|&i| {
+            let scene_path = &scene.paths()[i];
+            let in_fill = scene_path
+                .fill
+                .as_ref()
+                .is_some_and(|fill| fill_contains(&scene_path.path, point, fill.rule));
+            let in_stroke = || {
+                scene_path.stroke.as_ref().is_some_and(|stroke| {
+                    let styled = stroke.styled_path(&scene_path.path);
+
+                    stroke_contains(
+                        styled.as_ref().unwrap_or(&scene_path.path),
+                        point,
+                        stroke.width,
+                    )
+                })
+            };
+
+            in_fill || in_stroke()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blend::BlendMode,
+        path::PathBuilder,
+        scene::{Fill, ScenePath, Stroke},
+    };
+
+    /// `(2t, 4t(1 - t))`, an arch from (0, 0) to (2, 0) with its apex at (1, 1).
+    fn arch() -> Segment {
+        Segment::Quadratic((
+            Vector2::new(0., 0.),
+            Vector2::new(1., 2.),
+            Vector2::new(2., 0.),
+        ))
+    }
+
+    fn circles(radii: &[f32]) -> Path {
+        let mut builder = PathBuilder::new();
+
+        for &radius in radii {
+            builder.circle(Vector2::new(0., 0.), radius);
+        }
+
+        builder.build().unwrap()
+    }
+
+    fn scene_path(path: Path, fill: bool, stroke_width: Option<f32>) -> ScenePath {
+        ScenePath {
+            path,
+            fill: Some(Fill {
+                paint: [0., 0., 0., 1.].into(),
+                rule: FillRule::NonZero,
+            })
+            .filter(|_| fill),
+            stroke: stroke_width.map(|width| Stroke {
+                paint: [0., 0., 0., 1.].into(),
+                width,
+                dash_array: Vec::new(),
+                dash_offset: 0.,
+                trim_start: 0.,
+                trim_end: 1.,
+            }),
+            blend: BlendMode::Normal,
+        }
+    }
+
+    #[test]
+    fn nearest_t_clamped() {
+        let line = Segment::Line(Vector2::new(0., 0.), Vector2::new(2., 0.));
+
+        assert_eq!(nearest_t(&line, Vector2::new(-1., 1.)), 0.);
+        assert_eq!(nearest_t(&line, Vector2::new(3., -1.)), 1.);
+        assert_eq!(nearest_t(&line, Vector2::new(0.5, 1.)), 0.25);
+
+        assert_eq!(nearest_t(&arch(), Vector2::new(-1., -1.)), 0.);
+        assert_eq!(nearest_t(&arch(), Vector2::new(3., -1.)), 1.);
+        assert!((nearest_t(&arch(), Vector2::new(1., 2.)) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn nearest_point() {
+        let path = Path::from_polyline(vec![[0., 0.], [2., 0.], [2., 2.]]).unwrap();
+        let found = nearest(&path, Vector2::new(3., 1.5)).unwrap();
+
+        assert_eq!(found.index, 1);
+        assert_eq!(found.point, Vector2::new(2., 1.5));
+        assert_eq!(found.distance, 1.);
+        assert_eq!(nearest(&Path::default(), Vector2::new(0., 0.)), None);
+        assert_eq!(nearest(&path, Vector2::new(f32::NAN, 1.)), None);
+        assert_eq!(nearest(&path, Vector2::new(1., f32::INFINITY)), None);
+        // Meaningless, but doesn't panic
+        nearest_t(&arch(), Vector2::new(f32::NAN, 0.));
+    }
+
+    #[test]
+    fn fill() {
+        let rings = circles(&[2., 1.]);
+
+        assert!(fill_contains(
+            &rings,
+            Vector2::new(0., 0.),
+            FillRule::NonZero
+        ));
+        assert!(!fill_contains(
+            &rings,
+            Vector2::new(0., 0.),
+            FillRule::EvenOdd
+        ));
+        assert!(fill_contains(
+            &rings,
+            Vector2::new(1.5, 0.),
+            FillRule::EvenOdd
+        ));
+        assert!(!fill_contains(
+            &rings,
+            Vector2::new(2.5, 0.),
+            FillRule::NonZero
+        ));
+    }
+
+    #[test]
+    fn stroke() {
+        let path = Path::from_points(vec![[0., 0.], [1., 2.], [2., 0.]]).unwrap();
+
+        assert!(stroke_contains(&path, Vector2::new(1., 1.4), 1.));
+        assert!(!stroke_contains(&path, Vector2::new(1., 1.6), 1.));
+        assert!(stroke_contains(&path, Vector2::new(-0.4, 0.), 1.));
+        assert!(!stroke_contains(&path, Vector2::new(1., 0.), 1.));
+        assert!(!stroke_contains(&Path::default(), Vector2::new(0., 0.), 1.));
+    }
+
+    #[test]
+    fn pick_topmost() {
+        let mut scene = Scene::new(
+            [-4., -4., 8., 8.],
+            vec![
+                scene_path(circles(&[2.]), true, None),
+                scene_path(circles(&[1.]), true, None),
+                scene_path(circles(&[3.]), false, Some(0.5)),
+            ],
+        );
+
+        assert_eq!(pick(&scene, Vector2::new(0., 0.)), Some(1));
+        assert_eq!(pick(&scene, Vector2::new(1.5, 0.)), Some(0));
+        assert_eq!(pick(&scene, Vector2::new(3.1, 0.)), Some(2));
+        assert_eq!(pick(&scene, Vector2::new(2.5, 0.)), None);
+        assert_eq!(pick(&scene, Vector2::new(5., 0.)), None);
+        assert_eq!(pick(&scene, Vector2::new(f32::NAN, 0.)), None);
+
+        // Changing the paths rebuilds the index
+        scene.push(scene_path(circles(&[5.]), true, None));
+        assert_eq!(pick(&scene, Vector2::new(0., 0.)), Some(3));
+        scene.paths_mut().truncate(1);
+        assert_eq!(pick(&scene, Vector2::new(0., 0.)), Some(0));
+        assert_eq!(pick(&scene, Vector2::new(2.5, 0.)), None);
+    }
+
+    #[test]
+    fn pick_infinite_stroke() {
+        // Enough paths to split the index, with bounds that are infinite both ways
+        let scene = Scene::new(
+            [-4., -4., 8., 8.],
+            (0..6)
+                .map(|_| scene_path(circles(&[1.]), false, Some(f32::INFINITY)))
+                .collect(),
+        );
+
+        assert_eq!(pick(&scene, Vector2::new(100., 0.)), Some(5));
+    }
+}
@@ -0,0 +1,452 @@
+//! # Intersections
+//!
+//! Where segments cross each other, and how many times a path winds around a point.
+//!
+//! Lines are intersected with lines and quadratics exactly, by solving for where one crosses the
+//! other. Two quadratics meet where a quartic has its roots, which is awkward to solve robustly,
+//! so instead both are subdivided wherever their control polygons overlap until the pieces are
+//! tiny, and the result is refined with Newton's method.
+
+use crate::{
+    bounds::Bounds,
+    path::{Path, Segment},
+    tessellate::Quadratic,
+};
+use cgmath::{InnerSpace, Vector2};
+
+/// Quadratics are subdivided until their control polygons fit in a box this size, relative to
+/// the size of the two curves.
+const SUBDIVISION_TOLERANCE: f32 = 1e-5;
+
+/// The subdivision tolerance is never smaller than this many steps between `f32`s at the curves'
+/// distance from the origin, as rounding keeps the control polygons at least that big.
+const PRECISION_STEPS: f32 = 4.;
+
+/// Intersections closer together than this in `t` on both segments are treated as one.
+const DUPLICATE_EPSILON: f32 = 1e-4;
+
+/// Two quadratics cross at most 4 times. If the subdivision finds many more candidates than
+/// that, the curves overlap along part of their length, and they are treated as not crossing.
+const MAX_CANDIDATES: usize = 64;
+
+const NEWTON_ITERATIONS: usize = 4;
+
+/// The bounds of the control polygon, which contains the segment. This is looser than
+/// `Segment::bounds` but cheaper, which matters as it is found for every subdivision.
+fn hull_bounds(segment: &Segment) -> Bounds {
+    match *segment {
+        Segment::Line(start, end) => Bounds::from_point(start).including(end),
+        Segment::Quadratic((start, control, end)) => {
+            Bounds::from_point(start).including(control).including(end)
+        }
+    }
+}
+
+/// The real roots of `a * t^2 + b * t + c` that are in `0..=1`.
+fn unit_roots(a: f32, b: f32, c: f32) -> Vec<f32> {
+    let roots = if a.abs() <= f32::EPSILON * (b.abs() + c.abs()) {
+        if b == 0. {
+            vec![]
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let discriminant = b * b - 4. * a * c;
+
+        if discriminant < 0. {
+            vec![]
+        } else {
+            // Avoids cancellation between `-b` and the square root
+            let q = -(b + b.signum() * discriminant.sqrt()) / 2.;
+
+            if q == 0. {
+                vec![0.]
+            } else {
+                vec![q / a, c / q]
+            }
+        }
+    };
+
+    roots
+        .into_iter()
+        .filter(|t| (0. ..=1.).contains(t))
+        .collect()
+}
+
+/// The parameters `(s, t)` at which `a` and `b` cross, where `a.point(s) == b.point(t)`.
+/// Overlapping collinear lines and overlapping curves don't count as crossing.
+pub fn segments(a: &Segment, b: &Segment) -> Vec<(f32, f32)> {
+    if !hull_bounds(a).intersects(&hull_bounds(b)) {
+        return Vec::new();
+    }
+
+    match (*a, *b) {
+        (Segment::Line(a0, a1), Segment::Line(b0, b1)) => {
+            let (da, db) = (a1 - a0, b1 - b0);
+            let denominator = da.perp_dot(db);
+
+            if denominator == 0. {
+                return Vec::new();
+            }
+
+            let s = (b0 - a0).perp_dot(db) / denominator;
+            let t = (b0 - a0).perp_dot(da) / denominator;
+
+            if (0. ..=1.).contains(&s) && (0. ..=1.).contains(&t) {
+                vec![(s, t)]
+            } else {
+                Vec::new()
+            }
+        }
+        (Segment::Line(start, end), Segment::Quadratic(quadratic)) => {
+            line_quadratic(start, end, quadratic)
+        }
+        (Segment::Quadratic(quadratic), Segment::Line(start, end)) => {
+            line_quadratic(start, end, quadratic)
+                .into_iter()
+                .map(|(s, t)| (t, s))
+                .collect()
+        }
+        (Segment::Quadratic(_), Segment::Quadratic(_)) => quadratics(a, b),
+    }
+}
+
+/// The parameters `(s, t)` at which the line crosses the quadratic.
+fn line_quadratic(
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    (q0, q1, q2): Quadratic,
+) -> Vec<(f32, f32)> {
+    let direction = end - start;
+    let length2 = direction.magnitude2();
+
+    if length2 == 0. {
+        return Vec::new();
+    }
+
+    // The signed distance of the quadratic from the line (scaled by its length) is itself a
+    // quadratic in `t`, whose roots are the crossings
+    let distance = |p: Vector2<f32>| direction.perp_dot(p - start);
+    let (d0, d1, d2) = (distance(q0), distance(q1), distance(q2));
+    let quadratic = Segment::Quadratic((q0, q1, q2));
+
+    unit_roots(d0 - 2. * d1 + d2, 2. * (d1 - d0), d0)
+        .into_iter()
+        .filter_map(|t| {
+            let s = (quadratic.point(t) - start).dot(direction) / length2;
+
+            if (0. ..=1.).contains(&s) {
+                Some((s, t))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The parameters `(s, t)` at which two quadratics cross, found by subdivision.
+fn quadratics(a: &Segment, b: &Segment) -> Vec<(f32, f32)> {
+    let size = |bounds: Bounds| bounds.size().x.max(bounds.size().y);
+    let hull = hull_bounds(a).union(hull_bounds(b));
+    let magnitude = [hull.min.x, hull.min.y, hull.max.x, hull.max.y]
+        .iter()
+        .fold(0., |magnitude: f32, value| magnitude.max(value.abs()));
+    let tolerance = (size(hull_bounds(a)).max(size(hull_bounds(b))) * SUBDIVISION_TOLERANCE)
+        .max(magnitude * f32::EPSILON * PRECISION_STEPS);
+
+    let mut candidates = Vec::new();
+    subdivide(a, (0., 1.), b, (0., 1.), tolerance, &mut candidates);
+
+    if candidates.len() > MAX_CANDIDATES {
+        return Vec::new();
+    }
+
+    let mut out: Vec<(f32, f32)> = Vec::new();
+
+    for (s, t) in candidates {
+        let (s, t) = refine(a, b, s, t);
+        // Far from the origin, rounding can leave the same crossing further apart in `t`, but
+        // never more than `tolerance` apart in space
+        let duplicate = out.iter().any(|&(s2, t2)| {
+            ((s - s2).abs() < DUPLICATE_EPSILON && (t - t2).abs() < DUPLICATE_EPSILON)
+                || (a.point(s) - a.point(s2)).magnitude() <= tolerance
+        });
+
+        if !duplicate {
+            out.push((s, t));
+        }
+    }
+
+    out
+}
+
+/// Finds candidate crossings between the parts of `a` and `b` in the given ranges of `t`.
+fn subdivide(
+    a: &Segment,
+    a_range: (f32, f32),
+    b: &Segment,
+    b_range: (f32, f32),
+    tolerance: f32,
+    out: &mut Vec<(f32, f32)>,
+) {
+    if out.len() > MAX_CANDIDATES {
+        return;
+    }
+
+    let (a_part, b_part) = (a.slice(a_range.0, a_range.1), b.slice(b_range.0, b_range.1));
+    let (a_bounds, b_bounds) = (hull_bounds(&a_part), hull_bounds(&b_part));
+
+    if !a_bounds.intersects(&b_bounds) {
+        return;
+    }
+
+    let size = |bounds: Bounds| bounds.size().x.max(bounds.size().y);
+    let mid = |(from, to): (f32, f32)| (from + to) / 2.;
+    // Far from the origin the hulls can stay wider than `tolerance` until the ranges run out of
+    // precision, so a range whose midpoint rounds onto an end is treated as fully subdivided
+    let splittable = |range: (f32, f32)| mid(range) > range.0 && mid(range) < range.1;
+    let (split_a, split_b) = (splittable(a_range), splittable(b_range));
+
+    if (size(a_bounds) <= tolerance && size(b_bounds) <= tolerance) || !(split_a || split_b) {
+        out.push((mid(a_range), mid(b_range)));
+    } else if split_a && (size(a_bounds) >= size(b_bounds) || !split_b) {
+        let m = mid(a_range);
+        subdivide(a, (a_range.0, m), b, b_range, tolerance, out);
+        subdivide(a, (m, a_range.1), b, b_range, tolerance, out);
+    } else {
+        let m = mid(b_range);
+        subdivide(a, a_range, b, (b_range.0, m), tolerance, out);
+        subdivide(a, a_range, b, (m, b_range.1), tolerance, out);
+    }
+}
+
+/// Improves a crossing with Newton's method on `a.point(s) - b.point(t) == 0`.
+fn refine(a: &Segment, b: &Segment, mut s: f32, mut t: f32) -> (f32, f32) {
+    for _ in 0..NEWTON_ITERATIONS {
+        let error = a.point(s) - b.point(t);
+        let (da, db) = (a.derivative(s), -b.derivative(t));
+        let determinant = da.perp_dot(db);
+
+        if determinant.abs() <= f32::EPSILON {
+            break;
+        }
+
+        // Solve `[da db] * [ds dt] = -error` with Cramer's rule
+        let ds = -error.perp_dot(db) / determinant;
+        let dt = -da.perp_dot(error) / determinant;
+
+        s = (s + ds).clamp(0., 1.);
+        t = (t + dt).clamp(0., 1.);
+    }
+
+    (s, t)
+}
+
+/// The winding number of `path` around `point`: the number of times it goes around it
+/// counterclockwise (with the y axis pointing up) minus the number of times it goes around it
+/// clockwise. Open subpaths are treated as if they were closed with a straight line, as they are
+/// when filled.
+pub fn winding(path: &Path, point: Vector2<f32>) -> i32 {
+    path.subpaths()
+        .iter()
+        .map(|subpath| {
+            let closing = Segment::Line(subpath.segments().last().unwrap().end(), subpath.start());
+
+            subpath
+                .segments()
+                .iter()
+                .chain(Some(&closing))
+                .map(|segment| segment_winding(segment, point))
+                .sum::<i32>()
+        })
+        .sum()
+}
+
+/// How many times `segment` crosses the ray from `point` in the positive x direction, counting
+/// upwards crossings as +1 and downwards ones as -1. Crossings at the top of a segment are not
+/// counted, so that a ray through the point where two segments meet is only counted once.
+fn segment_winding(segment: &Segment, point: Vector2<f32>) -> i32 {
+    match *segment {
+        Segment::Line(start, end) => line_winding(start, end, point),
+        Segment::Quadratic((start, control, end)) => {
+            // Split at the extremum in y, so that each part is monotonic
+            let denominator = start.y - 2. * control.y + end.y;
+            let extremum = if denominator == 0. {
+                None
+            } else {
+                Some((start.y - control.y) / denominator).filter(|&t| t > 0. && t < 1.)
+            };
+
+            match extremum {
+                Some(t) => {
+                    monotonic_winding(segment, (0., t), point)
+                        + monotonic_winding(segment, (t, 1.), point)
+                }
+                None => monotonic_winding(segment, (0., 1.), point),
+            }
+        }
+    }
+}
+
+fn line_winding(start: Vector2<f32>, end: Vector2<f32>, point: Vector2<f32>) -> i32 {
+    let side = (end - start).perp_dot(point - start);
+
+    if start.y <= point.y && point.y < end.y && side > 0. {
+        1
+    } else if end.y <= point.y && point.y < start.y && side < 0. {
+        -1
+    } else {
+        0
+    }
+}
+
+/// `segment_winding` for the part of a quadratic between `from` and `to`, over which it is
+/// monotonic in y.
+fn monotonic_winding(segment: &Segment, (from, to): (f32, f32), point: Vector2<f32>) -> i32 {
+    let (start, end) = (segment.point(from), segment.point(to));
+    let (low, high, direction) = if start.y <= end.y {
+        (start.y, end.y, 1)
+    } else {
+        (end.y, start.y, -1)
+    };
+
+    if point.y < low || point.y >= high {
+        return 0;
+    }
+
+    // Find where the part crosses the height of the point by bisection, which is robust as it
+    // is monotonic
+    let (mut lo, mut hi) = if direction == 1 {
+        (from, to)
+    } else {
+        (to, from)
+    };
+
+    for _ in 0..32 {
+        let mid = (lo + hi) / 2.;
+
+        if segment.point(mid).y < point.y {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    if segment.point((lo + hi) / 2.).x > point.x {
+        direction
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn line(start: [f32; 2], end: [f32; 2]) -> Segment {
+        Segment::Line(start.into(), end.into())
+    }
+
+    fn quadratic(start: [f32; 2], control: [f32; 2], end: [f32; 2]) -> Segment {
+        Segment::Quadratic((start.into(), control.into(), end.into()))
+    }
+
+    /// Sorts the crossings by `s` and checks them against `expected`.
+    fn assert_crossings(mut actual: Vec<(f32, f32)>, expected: &[(f32, f32)]) {
+        actual.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+
+        for (&(s, t), &(expected_s, expected_t)) in actual.iter().zip(expected) {
+            assert!(
+                (s - expected_s).abs() < EPSILON && (t - expected_t).abs() < EPSILON,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn lines() {
+        let a = line([0., 0.], [2., 2.]);
+
+        assert_crossings(segments(&a, &line([0., 2.], [2., 0.])), &[(0.5, 0.5)]);
+        assert_crossings(
+            segments(&a, &line([0., 2.], [4., 0.])),
+            &[(2. / 3., 1. / 3.)],
+        );
+        assert_crossings(segments(&a, &line([1., 0.], [3., 2.])), &[]);
+        assert_crossings(segments(&a, &line([2., 0.], [1.5, 0.5])), &[]);
+    }
+
+    #[test]
+    fn line_and_quadratic() {
+        // `(2t, 4t(1 - t))`, which reaches a height of 0.75 at `t` = 0.25 and 0.75
+        let arch = quadratic([0., 0.], [1., 2.], [2., 0.]);
+        let line = line([-1., 0.75], [3., 0.75]);
+
+        assert_crossings(segments(&line, &arch), &[(0.375, 0.25), (0.625, 0.75)]);
+        assert_crossings(segments(&arch, &line), &[(0.25, 0.375), (0.75, 0.625)]);
+        assert_crossings(
+            segments(&arch, &super::tests::line([0., 2.], [2., 2.])),
+            &[],
+        );
+    }
+
+    #[test]
+    fn quadratics() {
+        // Both have `x = 2t`, and they meet where `4t(1 - t) = (1 - 2t)^2`
+        let arch = quadratic([0., 0.], [1., 2.], [2., 0.]);
+        let bowl = quadratic([0., 1.], [1., -1.], [2., 1.]);
+        let (t0, t1) = (0.5 - 0.125f32.sqrt(), 0.5 + 0.125f32.sqrt());
+
+        assert_crossings(segments(&arch, &bowl), &[(t0, t0), (t1, t1)]);
+
+        // Curves that don't cross, and a curve that overlaps itself everywhere
+        let lower = quadratic([0., -1.], [1., 1.], [2., -1.]);
+
+        assert_crossings(segments(&arch, &lower), &[]);
+        assert_crossings(segments(&arch, &arch), &[]);
+    }
+
+    #[test]
+    fn quadratics_far_from_origin() {
+        // The curves from `quadratics` moved to around 1e7, where `f32` can only step by 1
+        let offset = |[x, y]: [f32; 2]| [x * 1000. + 1e7, y * 1000. + 1e7];
+        let arch = quadratic(offset([0., 0.]), offset([1., 2.]), offset([2., 0.]));
+        let bowl = quadratic(offset([0., 1.]), offset([1., -1.]), offset([2., 1.]));
+        let (t0, t1) = (0.5 - 0.125f32.sqrt(), 0.5 + 0.125f32.sqrt());
+        let crossings = segments(&arch, &bowl);
+
+        assert_eq!(crossings.len(), 2, "{:?}", crossings);
+        for (&(s, t), &expected) in crossings.iter().zip(&[t0, t1]) {
+            assert!((s - expected).abs() < 1e-3 && (t - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn winding_numbers() {
+        let square = Path::from_polyline(vec![[0., 0.], [2., 0.], [2., 2.], [0., 2.]]).unwrap();
+        let mut builder = Path::builder();
+        builder
+            .circle(Vector2::new(1., 1.), 1.)
+            .circle(Vector2::new(1., 1.), 0.5);
+        let rings = builder.build().unwrap();
+
+        assert_eq!(winding(&square, Vector2::new(1., 1.)), 1);
+        assert_eq!(winding(&square, Vector2::new(3., 1.)), 0);
+        assert_eq!(
+            winding(
+                &square.map_points(|p| Vector2::new(-p.x, p.y)),
+                Vector2::new(-1., 1.)
+            ),
+            -1
+        );
+        assert_eq!(winding(&rings, Vector2::new(1., 1.)).abs(), 2);
+        assert_eq!(winding(&rings, Vector2::new(1.75, 1.)).abs(), 1);
+        assert_eq!(winding(&rings, Vector2::new(2.5, 1.)), 0);
+    }
+}
@@ -0,0 +1,54 @@
+//! # Quadratic decomposition
+//!
+//! Renders paths made of quadratic beziers on the GPU by decomposing their strokes into more
+//! quadratics, which are then drawn exactly in the fragment shader. Fills are drawn by
+//! accumulating the winding number of each pixel.
+//!
+//! Paths are built with a `PathBuilder`. The entry point for drawing is `Canvas`, which draws
+//! into any texture view that you give it, either a single stroked `Path` with `Canvas::render`
+//! or a whole styled `Scene` with `Canvas::render_scene`. `Headless` wraps a `Canvas` with its
+//! own device and render target for rendering straight to an image, and `SoftwareCanvas` does
+//! the same on the CPU.
+//!
+//! The interactive demo lives in `main.rs` and needs the `winit` feature, so that embedders only
+//! depend on `wgpu`.
+
+pub mod blend;
+pub mod boolean;
+pub mod bounds;
+// The caches are general-purpose, so the renderer doesn't use all of them
+#[allow(unused)]
+mod cache;
+pub mod color;
+pub mod dash;
+mod debug;
+pub mod headless;
+pub mod hit;
+mod intersect;
+pub mod measure;
+pub mod outline;
+pub mod paint;
+pub mod path;
+mod pipelines;
+mod raster;
+pub mod renderer;
+pub mod scene;
+pub mod spatial;
+pub mod svg;
+mod tessellate;
+pub mod trim;
+
+pub use blend::BlendMode;
+pub use bounds::Bounds;
+pub use color::ColorSpace;
+pub use debug::write_stroke_svg;
+pub use headless::{Headless, RgbaImage};
+pub use measure::PathMeasure;
+pub use outline::StrokeStyle;
+pub use paint::{Filter, Gradient, Image, Paint, Pattern, Spread, Stop};
+pub use path::{Path, PathBuilder, Segment, Subpath};
+pub use raster::SoftwareCanvas;
+pub use renderer::{Canvas, InvalidTessellation, Options, SampleCount};
+pub use scene::{Color, Fill, FillRule, Scene, ScenePath, Stroke};
+pub use spatial::SpatialIndex;
+pub use tessellate::{InvalidVertex, Quadratic, Spacing};
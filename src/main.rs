@@ -1,18 +1,28 @@
-use std::{iter, time};
-use wgpu::util::DeviceExt as _;
+use cgmath::Vector2;
+use quadratic_decomposition::{
+    headless, svg, write_stroke_svg, Canvas, ColorSpace, Headless, Options, Path, SampleCount,
+    Scene, SoftwareCanvas, Spacing,
+};
+use std::time;
 use winit::{
-    event::{self, DeviceEvent, Event, WindowEvent},
+    event::{self, Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder},
 };
 
-mod cache;
-mod pipelines;
-mod renderer;
-
 const DEFAULT_SIZE: (u32, u32) = (800, 800);
 
-async fn run(event_loop: EventLoop<()>, window: Window) {
+/// The curve that is drawn when no SVG is given, which ends at the mouse.
+fn demo_path(mouse_pos: Vector2<f32>) -> Path {
+    let mut builder = Path::builder();
+    builder
+        .move_to(Vector2::new(0.25, 0.))
+        .quad_to(Vector2::new(0.75, 0.75), mouse_pos);
+
+    builder.build().unwrap()
+}
+
+async fn run(event_loop: EventLoop<()>, window: Window, scene: Option<Scene>) {
     let size = window.inner_size();
     let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
     let surface = unsafe { instance.create_surface(&window) };
@@ -25,13 +35,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .unwrap();
 
     let out_path = if cfg!(debug_assertions) {
-        use std::convert::TryFrom;
-
-        Some(
-            std::path::PathBuf::try_from(env!("CARGO_MANIFEST_DIR"))
-                .unwrap()
-                .join("calls.dbg"),
-        )
+        Some(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("calls.dbg"))
     } else {
         None
     };
@@ -42,14 +46,18 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 limits: wgpu::Limits::default(),
                 shader_validation: cfg!(debug_assertions),
             },
-            out_path.as_ref().map(|p| &**p),
+            out_path.as_deref(),
         )
         .await
         .unwrap();
 
+    let mut options = Options {
+        validate: cfg!(debug_assertions),
+        ..Options::default()
+    };
     let mut sc_desc = wgpu::SwapChainDescriptor {
         usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-        format: pipelines::SWAPCHAIN_FORMAT,
+        format: options.color_space.format(),
         width: size.width,
         height: size.height,
         present_mode: wgpu::PresentMode::Mailbox,
@@ -57,8 +65,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let mut swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
-    let start = time::Instant::now();
-    let mut last_update_inst = time::Instant::now();
     let mut last_render_inst = time::Instant::now();
 
     const FPS: f64 = 60.;
@@ -69,13 +75,10 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let mut consecutive_timeouts = 0usize;
 
-    let mut renderer = renderer::Canvas::new(&device, (size.width, size.height));
+    let mut renderer = Canvas::new(&device, (size.width, size.height));
 
     let mut mouse_pos = cgmath::Vector2::new(0., 0.);
     let mut mouse_captured = false;
-    let mut options = renderer::Options {
-        num_segments: std::convert::TryFrom::try_from(2).unwrap(),
-    };
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::WaitUntil(last_render_inst + render_dt);
@@ -83,11 +86,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         let now = time::Instant::now();
 
         match event {
-            Event::MainEventsCleared => {
-                if now - last_render_inst > render_dt {
-                    window.request_redraw();
-                    last_render_inst = time::Instant::now();
-                }
+            Event::MainEventsCleared if now - last_render_inst > render_dt => {
+                window.request_redraw();
+                last_render_inst = time::Instant::now();
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
@@ -155,6 +156,46 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             options.num_segments = val;
                         }
                     }
+                    event::VirtualKeyCode::L => {
+                        options.spacing = match options.spacing {
+                            Spacing::Parameter => Spacing::ArcLength,
+                            Spacing::ArcLength => Spacing::Parameter,
+                        };
+                    }
+                    event::VirtualKeyCode::M => {
+                        options.sample_count = match options.sample_count {
+                            SampleCount::One => SampleCount::Four,
+                            SampleCount::Four => SampleCount::Eight,
+                            SampleCount::Eight => SampleCount::One,
+                        };
+                    }
+                    event::VirtualKeyCode::F => {
+                        options.fxaa = !options.fxaa;
+                    }
+                    event::VirtualKeyCode::C => {
+                        options.color_space = match options.color_space {
+                            ColorSpace::Srgb => ColorSpace::Gamma,
+                            ColorSpace::Gamma => ColorSpace::Srgb,
+                        };
+                        sc_desc.format = options.color_space.format();
+                        swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                    }
+                    event::VirtualKeyCode::D => {
+                        let out_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                            .join("decomposition.svg");
+                        let result = std::fs::File::create(&out_path).and_then(|file| {
+                            write_stroke_svg(
+                                std::io::BufWriter::new(file),
+                                &demo_path(mouse_pos),
+                                &options,
+                            )
+                        });
+
+                        match result {
+                            Ok(()) => println!("Wrote {}", out_path.display()),
+                            Err(e) => eprintln!("Could not write {}: {}", out_path.display(), e),
+                        }
+                    }
                     _ => {}
                 },
                 _ => {}
@@ -163,12 +204,21 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 Ok(frame) => {
                     consecutive_timeouts = 0;
 
-                    queue.submit(renderer.render(
-                        &device,
-                        &frame.output.view,
-                        [[0.25, 0.], [0.75, 0.75], mouse_pos.into()].iter().copied(),
-                        &options,
-                    ));
+                    let commands = if let Some(scene) = &scene {
+                        renderer.render_scene(&device, &frame.output.view, scene, &options)
+                    } else {
+                        renderer.render(
+                            &device,
+                            &frame.output.view,
+                            &demo_path(mouse_pos),
+                            &options,
+                        )
+                    };
+
+                    match commands {
+                        Ok(commands) => queue.submit(commands),
+                        Err(e) => eprintln!("{}", e),
+                    }
                 }
                 Err(_) => {
                     consecutive_timeouts += 1;
@@ -187,6 +237,30 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 }
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+
+    // If an SVG file is given, draw that instead of the interactive curve
+    let scene = args.next().map(|path| match svg::load(&path) {
+        Ok(scene) => scene,
+        Err(e) => panic!("Could not load {}: {}", path, e),
+    });
+
+    // If an output file is given too, render it to a PNG without opening a window
+    if let (Some(scene), Some(out_path)) = (&scene, args.next()) {
+        let options = Options::default();
+        let image = match futures::executor::block_on(Headless::new(DEFAULT_SIZE)) {
+            Ok(mut headless) => headless.render_scene(scene, &options).unwrap(),
+            Err(headless::Error::NoAdapter) => SoftwareCanvas::new(DEFAULT_SIZE)
+                .render_scene(scene, &options)
+                .unwrap(),
+            Err(e) => panic!("{}", e),
+        };
+
+        image.save_png(&out_path).unwrap();
+
+        return;
+    }
+
     let events = EventLoop::new();
     let (width, height) = DEFAULT_SIZE;
     let window = WindowBuilder::new()
@@ -195,5 +269,5 @@ fn main() {
         .build(&events)
         .unwrap();
 
-    futures::executor::block_on(run(events, window));
+    futures::executor::block_on(run(events, window, scene));
 }
@@ -0,0 +1,316 @@
+//! # Measurement
+//!
+//! Arc lengths of paths and their segments, and the conversion from a distance along a path back
+//! to the segment and `t` that it lands on. `SegmentLength` measures a single segment and
+//! `PathMeasure` a whole path, where distances run through every subpath in order, without
+//! counting the gaps between them.
+//!
+//! Quadratics have a closed form for their length, but it is badly conditioned when the control
+//! point is nearly collinear with the endpoints, so they are integrated numerically instead.
+
+use crate::path::{Path, Segment};
+use cgmath::{InnerSpace, Vector2};
+
+/// The number of equal steps in `t` that a quadratic is split into, each of which is integrated
+/// separately. The speed of a quadratic is the square root of a quadratic in `t`, which is smooth
+/// enough over each step for 5-point Gauss-Legendre to be accurate to well under 0.01%.
+const STEPS: usize = 16;
+
+/// The abscissae and weights of 5-point Gauss-Legendre quadrature on `[-1, 1]`.
+const GAUSS_LEGENDRE: [(f32, f32); 5] = [
+    (0., 0.568_888_9),
+    (-0.538_469_3, 0.478_628_7),
+    (0.538_469_3, 0.478_628_7),
+    (-0.906_179_8, 0.236_926_9),
+    (0.906_179_8, 0.236_926_9),
+];
+
+/// The number of Newton iterations used to refine `t` within a step.
+const NEWTON_ITERATIONS: usize = 3;
+
+/// The arc length of `segment` between `from` and `to`.
+fn integrate(segment: &Segment, from: f32, to: f32) -> f32 {
+    let (mid, half) = ((from + to) / 2., (to - from) / 2.);
+
+    GAUSS_LEGENDRE
+        .iter()
+        .map(|&(x, weight)| weight * segment.derivative(mid + half * x).magnitude())
+        .sum::<f32>()
+        * half
+}
+
+/// A segment along with its cumulative arc length at each step, for converting between
+/// distances along it and `t`.
+#[derive(Debug, Clone)]
+pub struct SegmentLength {
+    segment: Segment,
+    /// The length from the start to the end of each step, starting with 0 for the start of the
+    /// segment. Lines only have the total length.
+    lengths: Vec<f32>,
+}
+
+impl SegmentLength {
+    pub fn new(segment: Segment) -> Self {
+        let lengths = match segment {
+            Segment::Line(start, end) => vec![0., (end - start).magnitude()],
+            Segment::Quadratic(_) => {
+                let dt = (STEPS as f32).recip();
+
+                std::iter::once(0.)
+                    .chain((0..STEPS).scan(0., |total, i| {
+                        *total += integrate(&segment, i as f32 * dt, (i + 1) as f32 * dt);
+
+                        Some(*total)
+                    }))
+                    .collect()
+            }
+        };
+
+        Self { segment, lengths }
+    }
+
+    pub fn segment(&self) -> Segment {
+        self.segment
+    }
+
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// The `t` at which the arc length from the start is `distance`, which is clamped to the
+    /// length of the segment.
+    pub fn t_at(&self, distance: f32) -> f32 {
+        let length = self.length();
+
+        if length <= 0. || distance <= 0. {
+            return 0.;
+        } else if distance >= length {
+            return 1.;
+        }
+
+        if let Segment::Line(..) = self.segment {
+            return distance / length;
+        }
+
+        // The step containing `distance`, where `lengths[step] <= distance < lengths[step + 1]`
+        let step = self.lengths[1..]
+            .iter()
+            .position(|&l| l > distance)
+            .unwrap_or(STEPS - 1);
+        let dt = (STEPS as f32).recip();
+        let (from, to) = (step as f32 * dt, (step + 1) as f32 * dt);
+        let (start, end) = (self.lengths[step], self.lengths[step + 1]);
+
+        // Start from linear interpolation within the step, which is already close as the speed
+        // changes little over it.
+        let mut t = from + (distance - start) / (end - start) * dt;
+
+        for _ in 0..NEWTON_ITERATIONS {
+            let speed = self.segment.derivative(t).magnitude();
+
+            if speed <= 0. {
+                break;
+            }
+
+            let error = start + integrate(&self.segment, from, t) - distance;
+            t = (t - error / speed).clamp(from, to);
+        }
+
+        t
+    }
+}
+
+/// Where a distance along a path lands.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SegmentPosition {
+    /// The index of the segment in `Path::segments`.
+    pub index: usize,
+    pub segment: Segment,
+    pub t: f32,
+}
+
+/// The arc length of every segment of a path, for finding points by their distance along it.
+#[derive(Debug, Clone)]
+pub struct PathMeasure {
+    segments: Vec<SegmentLength>,
+    /// The distance from the start of the path to the start of each segment.
+    starts: Vec<f32>,
+    length: f32,
+}
+
+impl PathMeasure {
+    pub fn new(path: &Path) -> Self {
+        let segments = path.segments().map(SegmentLength::new).collect::<Vec<_>>();
+        let mut length = 0.;
+        let starts = segments
+            .iter()
+            .map(|segment| {
+                let start = length;
+                length += segment.length();
+
+                start
+            })
+            .collect();
+
+        Self {
+            segments,
+            starts,
+            length,
+        }
+    }
+
+    /// The total length of every subpath.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// The segment and `t` at `distance` along the path, which is clamped to its length. At the
+    /// boundary between two segments this is the start of the second. Returns `None` if the
+    /// path is empty.
+    pub fn segment_at(&self, distance: f32) -> Option<SegmentPosition> {
+        if self.segments.is_empty() {
+            return None;
+        }
+
+        let distance = distance.clamp(0., self.length);
+
+        // The last segment that starts at or before `distance`, skipping over any of zero length
+        let index = (0..self.segments.len())
+            .rev()
+            .find(|&i| self.starts[i] <= distance && self.segments[i].length() > 0.)
+            .unwrap_or(0);
+        let segment = &self.segments[index];
+
+        Some(SegmentPosition {
+            index,
+            segment: segment.segment(),
+            t: segment.t_at(distance - self.starts[index]),
+        })
+    }
+
+    /// The point at `distance` along the path, which is clamped to its length.
+    pub fn point_at(&self, distance: f32) -> Option<Vector2<f32>> {
+        self.segment_at(distance)
+            .map(|position| position.segment.point(position.t))
+    }
+
+    /// The unit tangent at `distance` along the path, which is clamped to its length, pointing
+    /// in the direction of the path. Falls back to the direction of the segment's chord where
+    /// the derivative is zero.
+    pub fn tangent_at(&self, distance: f32) -> Option<Vector2<f32>> {
+        self.segment_at(distance).map(|position| {
+            let segment = position.segment;
+            let tangent = segment.derivative(position.t);
+            let tangent = if tangent.magnitude2() > 0. {
+                tangent
+            } else {
+                segment.end() - segment.start()
+            };
+
+            if tangent.magnitude2() > 0. {
+                tangent.normalize()
+            } else {
+                Vector2::unit_x()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::PathBuilder;
+
+    const EPSILON: f32 = 1e-4;
+
+    /// The parabola `y = x²` from `x = 0` to `x = 1`, where `t = x`.
+    fn parabola() -> Segment {
+        Segment::Quadratic((
+            Vector2::new(0., 0.),
+            Vector2::new(0.5, 0.),
+            Vector2::new(1., 1.),
+        ))
+    }
+
+    /// The exact arc length of `y = x²` from `x = 0` to `x`.
+    fn parabola_length(x: f32) -> f32 {
+        (2. * x * (1. + 4. * x * x).sqrt() + (2. * x).asinh()) / 4.
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn line_length() {
+        let line = SegmentLength::new(Segment::Line(Vector2::new(1., 1.), Vector2::new(4., 5.)));
+
+        assert_eq!(line.length(), 5.);
+        assert_eq!(line.t_at(2.5), 0.5);
+    }
+
+    #[test]
+    fn quadratic_length() {
+        assert_near(SegmentLength::new(parabola()).length(), parabola_length(1.));
+
+        // Evenly spaced collinear points move at a constant speed
+        let straight = SegmentLength::new(Segment::Quadratic((
+            Vector2::new(0., 0.),
+            Vector2::new(1., 0.),
+            Vector2::new(2., 0.),
+        )));
+
+        assert_near(straight.length(), 2.);
+        assert_near(straight.t_at(0.5), 0.25);
+    }
+
+    #[test]
+    fn t_at() {
+        let parabola = SegmentLength::new(parabola());
+
+        for &x in &[0.1, 0.25, 0.5, 0.9] {
+            assert_near(parabola.t_at(parabola_length(x)), x);
+        }
+
+        assert_eq!(parabola.t_at(-1.), 0.);
+        assert_eq!(parabola.t_at(10.), 1.);
+    }
+
+    #[test]
+    fn point_at_length() {
+        let mut builder = PathBuilder::new();
+        builder
+            .move_to(Vector2::new(-2., 0.))
+            .line_to(Vector2::new(0., 0.))
+            .quad_to(Vector2::new(0.5, 0.), Vector2::new(1., 1.));
+        let measure = PathMeasure::new(&builder.build().unwrap());
+
+        assert_near(measure.length(), 2. + parabola_length(1.));
+        assert_eq!(measure.point_at(1.), Some(Vector2::new(-1., 0.)));
+        assert_eq!(measure.point_at(-1.), Some(Vector2::new(-2., 0.)));
+        assert_eq!(measure.point_at(100.), Some(Vector2::new(1., 1.)));
+
+        let position = measure.segment_at(2. + parabola_length(0.5)).unwrap();
+        let point = measure.point_at(2. + parabola_length(0.5)).unwrap();
+
+        assert_eq!(position.index, 1);
+        assert_near(position.t, 0.5);
+        assert_near(point.x, 0.5);
+        assert_near(point.y, 0.25);
+
+        // At the boundary between two segments, the position is the start of the second
+        assert_eq!(measure.segment_at(2.).unwrap().index, 1);
+        assert_eq!(measure.segment_at(2.).unwrap().t, 0.);
+    }
+
+    #[test]
+    fn tangent_at() {
+        let measure = PathMeasure::new(&Path::from_polyline(vec![[0., 0.], [0., 2.]]).unwrap());
+
+        assert_eq!(measure.tangent_at(1.), Some(Vector2::new(0., 1.)));
+        assert_eq!(
+            PathMeasure::new(&Path::builder().build().unwrap()).point_at(0.),
+            None
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! # Stroke outlines
+//!
+//! Converts a stroke into a path that covers the same area when filled with the non-zero rule,
+//! so that it can be exported, combined with other shapes or drawn by another renderer. The
+//! offset curves are the same ones that `Canvas` draws, and are joined up with the joins and caps
+//! of a `StrokeStyle`, which the GPU stroke doesn't draw.
+//!
+//! Each open subpath becomes one closed subpath, which runs along the left of the stroke, around
+//! the end cap, back along the right and around the start cap. Each closed subpath becomes two,
+//! one for each side, which wind in opposite directions so that the inside is left empty.
+
+use crate::{
+    path::{Path, PathBuilder, Segment},
+    renderer::Options,
+    tessellate::{self, StrokeSegment},
+};
+use cgmath::{InnerSpace, Vector2};
+use std::f32::consts::PI;
+
+/// The sine of the smallest turn between two pieces that gets a join. Smaller turns are simply
+/// bridged with a line, as they are usually rounding error between curves that are meant to
+/// meet smoothly.
+const MIN_JOIN_TURN: f32 = 1e-3;
+
+/// The shape of the outside of the corner between two segments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Join {
+    /// A point where the offset curves meet when extended, as long as it is no further from the
+    /// corner than the given multiple of half the width. Sharper corners are bevelled instead.
+    /// This is the same as SVG's `stroke-miterlimit`.
+    Miter(f32),
+    Round,
+    Bevel,
+}
+
+/// The shape of the ends of open subpaths.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cap {
+    Butt,
+    Round,
+    /// Extends the stroke by half its width past the end.
+    Square,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeStyle {
+    /// The full width of the stroke.
+    pub width: f32,
+    pub join: Join,
+    pub cap: Cap,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.,
+            join: Join::Miter(4.),
+            cap: Cap::Butt,
+        }
+    }
+}
+
+/// A piece of the stroke, with its offset curves and the points on the path at either end.
+#[derive(Debug, Copy, Clone)]
+struct Piece {
+    left: Segment,
+    right: Segment,
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+}
+
+impl Piece {
+    /// The same piece of stroke, traced from its end to its start.
+    fn reversed(&self) -> Self {
+        Self {
+            left: self.right.reversed(),
+            right: self.left.reversed(),
+            start: self.end,
+            end: self.start,
+        }
+    }
+
+    /// The direction of the piece at `t`, which is 0 at the start and 1 at the end.
+    fn tangent(&self, t: f32) -> Vector2<f32> {
+        let tangent = self.left.derivative(t) + self.right.derivative(t);
+
+        if tangent.magnitude2() > 0. {
+            tangent.normalize()
+        } else {
+            direction(self.end - self.start)
+        }
+    }
+}
+
+/// `v` as a unit vector, or the x axis if it has no direction.
+fn direction(v: Vector2<f32>) -> Vector2<f32> {
+    if v.magnitude2() > 0. {
+        v.normalize()
+    } else {
+        Vector2::unit_x()
+    }
+}
+
+/// The angle of `v` from the x axis.
+fn angle(v: Vector2<f32>) -> f32 {
+    v.y.atan2(v.x)
+}
+
+/// Returns a path whose non-zero fill covers the stroke of `path` with the given style. The
+/// offset curves are split up according to `options`, in the same way as when `Canvas` draws the
+/// stroke.
+pub fn outline(path: &Path, style: &StrokeStyle, options: &Options) -> Path {
+    let width = style.width / 2.;
+    let mut builder = PathBuilder::new();
+
+    if width.is_nan() || width <= 0. {
+        return Path::default();
+    }
+
+    for subpath in path.subpaths() {
+        let pieces = subpath
+            .segments()
+            .iter()
+            .flat_map(|&segment| {
+                let strokes = tessellate::decompose_segment(
+                    segment,
+                    width,
+                    options.num_segments,
+                    options.spacing,
+                );
+                let count = strokes.len();
+
+                strokes
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, stroke)| piece(segment, stroke, i == 0, i + 1 == count))
+            })
+            .collect::<Vec<_>>();
+
+        if pieces.is_empty() {
+            continue;
+        }
+
+        let reversed = pieces.iter().rev().map(Piece::reversed).collect::<Vec<_>>();
+
+        if subpath.is_closed() {
+            for pieces in &[pieces, reversed] {
+                builder.move_to(pieces[0].left.start());
+                side(&mut builder, pieces, style.join, width, true);
+                builder.close();
+            }
+        } else {
+            builder.move_to(pieces[0].left.start());
+            side(&mut builder, &pieces, style.join, width, false);
+            cap(&mut builder, pieces.last().unwrap(), style.cap, width);
+            side(&mut builder, &reversed, style.join, width, false);
+            cap(&mut builder, reversed.last().unwrap(), style.cap, width);
+            builder.close();
+        }
+    }
+
+    // Offsetting a path with huge coordinates can overflow, in which case there is no outline
+    builder.build().unwrap_or_default()
+}
+
+/// The piece of stroke for one of the pieces that `segment` was decomposed into. The ends of the
+/// segment are exact, while the ends of pieces in the middle are only needed between the two
+/// lines of a quadratic that doubles back on itself, which are offset by the full width.
+fn piece(segment: Segment, stroke: StrokeSegment, first: bool, last: bool) -> Piece {
+    let (left, right) = stroke.sides();
+    let midpoint = |a: Vector2<f32>, b: Vector2<f32>| (a + b) / 2.;
+
+    Piece {
+        left,
+        right,
+        start: if first {
+            segment.start()
+        } else {
+            midpoint(left.start(), right.start())
+        },
+        end: if last {
+            segment.end()
+        } else {
+            midpoint(left.end(), right.end())
+        },
+    }
+}
+
+/// Appends the left side of `pieces`, with a join between each pair, starting with a line from
+/// the current position. When the side is `closed`, the last piece is joined back to the first.
+fn side(builder: &mut PathBuilder, pieces: &[Piece], join_style: Join, width: f32, closed: bool) {
+    builder.line_to(pieces[0].left.start());
+
+    for (i, piece) in pieces.iter().enumerate() {
+        builder.segment_to(piece.left);
+
+        match pieces.get(i + 1) {
+            Some(next) => join(builder, piece, next, join_style, width),
+            None if closed => join(builder, piece, &pieces[0], join_style, width),
+            None => {}
+        }
+    }
+}
+
+/// Appends the join on the left side between `from` and `to`, ending at the start of the left
+/// side of `to`.
+fn join(builder: &mut PathBuilder, from: &Piece, to: &Piece, join: Join, width: f32) {
+    let (start, end) = (from.left.end(), to.left.start());
+
+    if start == end {
+        return;
+    }
+
+    let (d0, d1) = (from.tangent(1.), to.tangent(0.));
+    let turn = d0.perp_dot(d1);
+    let centre = from.end;
+
+    if turn.abs() < MIN_JOIN_TURN && d0.dot(d1) > 0. {
+        builder.line_to(end);
+    } else if turn > 0. {
+        // Turning left, so this is the inside of the corner. Going through the corner itself
+        // makes sure that the area between the two offset curves is covered.
+        builder.line_to(centre).line_to(end);
+    } else {
+        match join {
+            Join::Bevel => {}
+            Join::Miter(limit) => {
+                // Where the tangent lines at `start` and `end` intersect
+                let miter = start + d0 * (end - start).perp_dot(d1) / d0.perp_dot(d1);
+
+                if (miter - centre).magnitude() <= limit * width {
+                    builder.line_to(miter);
+                }
+            }
+            Join::Round => {
+                let (a, b) = (start - centre, end - centre);
+
+                builder.arc(
+                    centre,
+                    Vector2::new(width, width),
+                    0.,
+                    angle(a),
+                    a.perp_dot(b).atan2(a.dot(b)),
+                );
+            }
+        }
+
+        builder.line_to(end);
+    }
+}
+
+/// Appends the cap at the end of `piece`, from the end of its left side to the end of its right.
+fn cap(builder: &mut PathBuilder, piece: &Piece, cap: Cap, width: f32) {
+    let (left, right) = (piece.left.end(), piece.right.end());
+    let centre = piece.end;
+    let normal = direction(left - right);
+    let forward = Vector2::new(normal.y, -normal.x) * width;
+
+    match cap {
+        Cap::Butt => {}
+        Cap::Square => {
+            builder.line_to(left + forward).line_to(right + forward);
+        }
+        Cap::Round => {
+            builder.arc(centre, Vector2::new(width, width), 0., angle(normal), -PI);
+        }
+    }
+
+    builder.line_to(right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hit::fill_contains, scene::FillRule};
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Round joins and caps are approximated by quadratics, which bulge slightly past the circle.
+    const ARC_EPSILON: f32 = 1e-3;
+
+    /// A corner that turns right at (4, 0), so the outside of the corner is on the left.
+    fn corner() -> Path {
+        Path::from_polyline(vec![[0., 0.], [4., 0.], [4., -4.]]).unwrap()
+    }
+
+    fn outlined(path: &Path, join: Join, cap: Cap) -> Path {
+        let style = StrokeStyle {
+            width: 2.,
+            join,
+            cap,
+        };
+
+        outline(path, &style, &Options::default())
+    }
+
+    fn assert_bounds(path: &Path, min: [f32; 2], max: [f32; 2], epsilon: f32) {
+        let bounds = path.bounds().unwrap();
+        let near = |a: Vector2<f32>, b: [f32; 2]| {
+            (a.x - b[0]).abs() < epsilon && (a.y - b[1]).abs() < epsilon
+        };
+
+        assert!(
+            near(bounds.min, min) && near(bounds.max, max),
+            "{:?}",
+            bounds
+        );
+    }
+
+    fn inside(path: &Path, x: f32, y: f32) -> bool {
+        fill_contains(path, Vector2::new(x, y), FillRule::NonZero)
+    }
+
+    #[test]
+    fn caps() {
+        let line = Path::from_polyline(vec![[0., 0.], [4., 0.]]).unwrap();
+
+        let butt = outlined(&line, Join::Bevel, Cap::Butt);
+        assert_eq!(butt.subpaths().len(), 1);
+        assert!(butt.subpaths()[0].is_closed());
+        assert_bounds(&butt, [0., -1.], [4., 1.], EPSILON);
+        assert!(inside(&butt, 0.1, 0.9) && !inside(&butt, -0.1, 0.));
+
+        let square = outlined(&line, Join::Bevel, Cap::Square);
+        assert_bounds(&square, [-1., -1.], [5., 1.], EPSILON);
+        assert!(inside(&square, -0.9, 0.9) && inside(&square, 4.9, -0.9));
+
+        let round = outlined(&line, Join::Bevel, Cap::Round);
+        assert_bounds(&round, [-1., -1.], [5., 1.], ARC_EPSILON);
+        assert!(inside(&round, -0.9, 0.) && inside(&round, 4.6, 0.6));
+        assert!(!inside(&round, -0.8, 0.8) && !inside(&round, 4.8, -0.8));
+    }
+
+    #[test]
+    fn miter_join() {
+        let miter = outlined(&corner(), Join::Miter(4.), Cap::Butt);
+
+        // The offset edges meet at (5, 1), outside the corner
+        assert_bounds(&miter, [0., -4.], [5., 1.], EPSILON);
+        assert!(inside(&miter, 4.9, 0.9));
+        // The inside of the corner is covered too
+        assert!(inside(&miter, 3.1, -0.9) && !inside(&miter, 2.9, -1.1));
+    }
+
+    #[test]
+    fn miter_limit_bevels() {
+        // The miter is √2 half-widths from the corner, which is past a limit of 1
+        let bevel = outlined(&corner(), Join::Miter(1.), Cap::Butt);
+
+        assert_bounds(&bevel, [0., -4.], [5., 1.], EPSILON);
+        assert!(!inside(&bevel, 4.9, 0.9));
+        // Just inside the bevel from (4, 1) to (5, 0)
+        assert!(inside(&bevel, 4.4, 0.4));
+        assert_eq!(bevel, outlined(&corner(), Join::Bevel, Cap::Butt));
+        assert_eq!(
+            outlined(&corner(), Join::Miter(1.5), Cap::Butt),
+            outlined(&corner(), Join::Miter(4.), Cap::Butt)
+        );
+    }
+
+    #[test]
+    fn round_join() {
+        let round = outlined(&corner(), Join::Round, Cap::Butt);
+
+        assert_bounds(&round, [0., -4.], [5., 1.], ARC_EPSILON);
+        assert!(inside(&round, 4.6, 0.6));
+        assert!(!inside(&round, 4.9, 0.9));
+    }
+
+    #[test]
+    fn closed_subpath() {
+        let mut builder = Path::builder();
+        builder
+            .move_to(Vector2::new(0., 0.))
+            .line_to(Vector2::new(4., 0.))
+            .line_to(Vector2::new(4., 4.))
+            .line_to(Vector2::new(0., 4.))
+            .close();
+        let square = outlined(&builder.build().unwrap(), Join::Miter(4.), Cap::Round);
+
+        // One subpath for each side, without caps, and with the inside left empty
+        assert_eq!(square.subpaths().len(), 2);
+        assert!(square.subpaths().iter().all(|subpath| subpath.is_closed()));
+        assert_bounds(&square, [-1., -1.], [5., 5.], EPSILON);
+        assert!(inside(&square, -0.9, -0.9) && inside(&square, 4.9, 4.9));
+        assert!(inside(&square, 2., 0.5) && inside(&square, 2., -0.5));
+        assert!(!inside(&square, 2., 2.) && !inside(&square, 2., 1.1));
+    }
+
+    #[test]
+    fn empty() {
+        let line = Path::from_polyline(vec![[0., 0.], [4., 0.]]).unwrap();
+        let style = |width| StrokeStyle {
+            width,
+            ..StrokeStyle::default()
+        };
+
+        assert_eq!(
+            outline(&line, &style(0.), &Options::default()),
+            Path::default()
+        );
+        assert_eq!(
+            outline(&line, &style(f32::NAN), &Options::default()),
+            Path::default()
+        );
+        assert_eq!(
+            outline(&Path::default(), &style(1.), &Options::default()),
+            Path::default()
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! # Paints
+//!
+//! What the area covered by a fill or a stroke is coloured with: a solid colour, a linear or
+//! radial gradient, or an image pattern. Gradients and patterns are defined in the same space as
+//! the path they paint.
+//!
+//! The stops of each gradient are baked into a row of `RAMP_WIDTH` premultiplied colours, which
+//! the blit pass interpolates between. This keeps the number of stops out of the shader, at the
+//! cost of blurring stops that are closer together than a texel. Images are premultiplied in the
+//! same way and packed into a texture by `cache::TextureCache`, and the blit pass does its own
+//! filtering so that the edges of each image wrap around within it.
+
+use crate::{color::ColorSpace, scene::Color};
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector2};
+use std::sync::Arc;
+
+/// The number of colours in the ramp that a gradient is baked into.
+pub const RAMP_WIDTH: usize = 256;
+
+/// How a gradient continues past its ends, as in SVG's `spreadMethod`, or a pattern past the
+/// edges of its image.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Spread {
+    /// The colours at the ends are extended forever.
+    #[default]
+    Pad = 0,
+    /// The gradient starts again from the first stop.
+    Repeat = 1,
+    /// The gradient runs backwards and forwards.
+    Reflect = 2,
+}
+
+impl Spread {
+    /// Maps a position along the gradient onto `0..=1`. This must match `spread` in `blit.glsl`.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Self::Pad => t.clamp(0., 1.),
+            Self::Repeat => t - t.floor(),
+            Self::Reflect => 1. - ((t - 2. * (t / 2.).floor()) - 1.).abs(),
+        }
+    }
+
+    /// Maps the index of a texel onto `0..size`. This must match `wrap` in `blit.glsl`.
+    pub fn wrap(self, i: i32, size: i32) -> i32 {
+        match self {
+            Self::Pad => i.max(0).min(size - 1),
+            Self::Repeat => i.rem_euclid(size),
+            Self::Reflect => {
+                let i = i.rem_euclid(2 * size);
+
+                if i < size {
+                    i
+                } else {
+                    2 * size - 1 - i
+                }
+            }
+        }
+    }
+}
+
+/// How a pattern is sampled between the centres of the pixels of its image.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Each point takes the colour of the pixel it's in.
+    Nearest = 0,
+    /// The four nearest pixels are interpolated between.
+    #[default]
+    Linear = 1,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Straight sRGB colours, row by row from the top left, as in a PNG.
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Returns `None` unless there are exactly `width * height` pixels.
+    pub fn new(width: u32, height: u32, pixels: Vec<[u8; 4]>) -> Option<Self> {
+        if pixels.len() == width as usize * height as usize {
+            Some(Self {
+                width,
+                height,
+                pixels,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The premultiplied colours that the blit pass samples, in the same order as `pixels`.
+    pub(crate) fn texels(&self, color_space: ColorSpace) -> Vec<[f32; 4]> {
+        self.pixels
+            .iter()
+            .map(|pixel| {
+                let channel = |i: usize| pixel[i] as f32 / 255.;
+
+                color_space.premultiply([channel(0), channel(1), channel(2), channel(3)])
+            })
+            .collect()
+    }
+}
+
+/// An image repeated over the area it paints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    /// Shared so that the same image can be used by many paths, and only uploaded once a frame.
+    pub image: Arc<Image>,
+    /// Maps the pixels of the image, with the origin at its top left corner, onto the space of
+    /// the path. Any affine transformation is allowed.
+    pub transform: Matrix3<f32>,
+    pub spread: Spread,
+    pub filter: Filter,
+}
+
+impl Pattern {
+    /// A repeating pattern of `image`, with its pixels mapped onto the path by `transform`.
+    pub fn new(image: Arc<Image>, transform: Matrix3<f32>) -> Self {
+        Self {
+            image,
+            transform,
+            spread: Spread::Repeat,
+            filter: Filter::default(),
+        }
+    }
+
+    /// Whether the pattern paints nothing, as its image is empty or it has no area.
+    pub fn is_degenerate(&self) -> bool {
+        self.image.pixels.is_empty() || !self.transform.determinant().is_normal()
+    }
+
+    /// The pattern after mapping its points with `f`, which must be affine.
+    pub fn map_points(&self, f: impl Fn(Vector2<f32>) -> Vector2<f32>) -> Self {
+        let map = |x: f32, y: f32| f((self.transform * Vector2::new(x, y).extend(1.)).truncate());
+        let origin = map(0., 0.);
+
+        Self {
+            transform: Matrix3::from_cols(
+                (map(1., 0.) - origin).extend(0.),
+                (map(0., 1.) - origin).extend(0.),
+                origin.extend(1.),
+            ),
+            ..self.clone()
+        }
+    }
+}
+
+/// Samples texels from `Image::texels` at `point`, in the pixels of the image. This must match
+/// `pattern` in `blit.glsl`.
+pub(crate) fn sample_image(
+    texels: &[[f32; 4]],
+    (width, height): (u32, u32),
+    spread: Spread,
+    filter: Filter,
+    point: Vector2<f32>,
+) -> [f32; 4] {
+    let texel = |x: i32, y: i32| {
+        let x = spread.wrap(x, width as i32);
+        let y = spread.wrap(y, height as i32);
+
+        texels[y as usize * width as usize + x as usize]
+    };
+
+    match filter {
+        Filter::Nearest => texel(point.x.floor() as i32, point.y.floor() as i32),
+        Filter::Linear => {
+            let p = point - Vector2::new(0.5, 0.5);
+            let (x, y) = (p.x.floor() as i32, p.y.floor() as i32);
+            let f = Vector2::new(p.x - p.x.floor(), p.y - p.y.floor());
+            let mix = |a: [f32; 4], b: [f32; 4], f: f32| {
+                let mix = |c: usize| a[c] + (b[c] - a[c]) * f;
+
+                [mix(0), mix(1), mix(2), mix(3)]
+            };
+
+            mix(
+                mix(texel(x, y), texel(x + 1, y), f.x),
+                mix(texel(x, y + 1), texel(x + 1, y + 1), f.x),
+                f.y,
+            )
+        }
+    }
+}
+
+/// A colour at a fraction of the way along a gradient.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GradientShape {
+    /// Runs from the first stop at `start` to the last at `end`, and is constant along lines
+    /// perpendicular to that.
+    Linear {
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+    },
+    /// Runs from the first stop at `centre` to the last on the circle of `radius` around it.
+    Radial { centre: Vector2<f32>, radius: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    /// The stops in order of offset. As in SVG, an offset less than the one before it is treated
+    /// as equal to it, which makes a hard edge.
+    pub stops: Vec<Stop>,
+    pub spread: Spread,
+}
+
+impl Gradient {
+    pub fn linear(start: Vector2<f32>, end: Vector2<f32>, stops: Vec<Stop>) -> Self {
+        Self {
+            shape: GradientShape::Linear { start, end },
+            stops,
+            spread: Spread::default(),
+        }
+    }
+
+    pub fn radial(centre: Vector2<f32>, radius: f32, stops: Vec<Stop>) -> Self {
+        Self {
+            shape: GradientShape::Radial { centre, radius },
+            stops,
+            spread: Spread::default(),
+        }
+    }
+
+    /// Whether the gradient has no length, in which case SVG paints it with the colour of its
+    /// last stop.
+    pub fn is_degenerate(&self) -> bool {
+        match self.shape {
+            GradientShape::Linear { start, end } => !(end - start).magnitude2().is_normal(),
+            GradientShape::Radial { radius, .. } => !radius.is_normal(),
+        }
+    }
+
+    /// How far along the gradient `point` is, before `spread` is applied, so that the first stop
+    /// is at 0 and the last at 1. A gradient with no length is at 0 everywhere, although
+    /// `Paint::as_solid` paints those with a solid colour instead. This must match `paint` in
+    /// `blit.glsl`.
+    pub fn position(&self, point: Vector2<f32>) -> f32 {
+        match self.shape {
+            GradientShape::Linear { start, end } => {
+                let direction = end - start;
+                let length2 = direction.magnitude2();
+
+                if length2 > 0. {
+                    (point - start).dot(direction) / length2
+                } else {
+                    0.
+                }
+            }
+            GradientShape::Radial { centre, radius } if radius > 0. => {
+                (point - centre).magnitude() / radius
+            }
+            GradientShape::Radial { .. } => 0.,
+        }
+    }
+
+    /// The straight colour at `t`, from 0 to 1, interpolated between the stops on either side.
+    pub fn color_at(&self, t: f32) -> Color {
+        let mut stops = self.stops.iter();
+        let first = match stops.next() {
+            Some(first) => *first,
+            None => return [0.; 4],
+        };
+        let mut before = Stop {
+            offset: first.offset.clamp(0., 1.),
+            ..first
+        };
+
+        if t <= before.offset {
+            return before.color;
+        }
+
+        for stop in stops {
+            let after = Stop {
+                offset: stop.offset.max(before.offset).min(1.),
+                ..*stop
+            };
+
+            if t <= after.offset {
+                let f = (t - before.offset) / (after.offset - before.offset);
+                let mix = |i: usize| before.color[i] + (after.color[i] - before.color[i]) * f;
+
+                return [mix(0), mix(1), mix(2), mix(3)];
+            }
+
+            before = after;
+        }
+
+        before.color
+    }
+
+    /// The premultiplied colours that the blit pass interpolates between, evenly spaced from
+    /// the first stop to the last.
+    pub(crate) fn ramp(&self, color_space: ColorSpace) -> Vec<[f32; 4]> {
+        (0..RAMP_WIDTH)
+            .map(|i| color_space.premultiply(self.color_at(i as f32 / (RAMP_WIDTH - 1) as f32)))
+            .collect()
+    }
+
+    /// The gradient after mapping its points with `f`, which must only translate, rotate,
+    /// reflect or uniformly scale.
+    pub fn map_points(&self, f: impl Fn(Vector2<f32>) -> Vector2<f32>) -> Self {
+        let shape = match self.shape {
+            GradientShape::Linear { start, end } => GradientShape::Linear {
+                start: f(start),
+                end: f(end),
+            },
+            GradientShape::Radial { centre, radius } => GradientShape::Radial {
+                centre: f(centre),
+                radius: (f(centre + Vector2::new(radius, 0.)) - f(centre)).magnitude(),
+            },
+        };
+
+        Self {
+            shape,
+            ..self.clone()
+        }
+    }
+}
+
+/// Interpolates between the colours of a ramp from `Gradient::ramp` at `t`, from 0 to 1. This
+/// must match `paint` in `blit.glsl`.
+pub(crate) fn sample_ramp(ramp: &[[f32; 4]], t: f32) -> [f32; 4] {
+    let x = t * (RAMP_WIDTH - 1) as f32;
+    let i = (x.floor() as usize).min(RAMP_WIDTH - 2);
+    let f = x - i as f32;
+    let mix = |c: usize| ramp[i][c] + (ramp[i + 1][c] - ramp[i][c]) * f;
+
+    [mix(0), mix(1), mix(2), mix(3)]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    Gradient(Gradient),
+    Pattern(Pattern),
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+
+impl From<Gradient> for Paint {
+    fn from(gradient: Gradient) -> Self {
+        Self::Gradient(gradient)
+    }
+}
+
+impl From<Pattern> for Paint {
+    fn from(pattern: Pattern) -> Self {
+        Self::Pattern(pattern)
+    }
+}
+
+impl Paint {
+    /// The paint after mapping any points that it has with `f`, as in `Gradient::map_points`.
+    pub fn map_points(&self, f: impl Fn(Vector2<f32>) -> Vector2<f32>) -> Self {
+        match self {
+            Self::Solid(color) => Self::Solid(*color),
+            Self::Gradient(gradient) => Self::Gradient(gradient.map_points(f)),
+            Self::Pattern(pattern) => Self::Pattern(pattern.map_points(f)),
+        }
+    }
+
+    /// A solid colour that paints the same as this, if there is one. Degenerate gradients are
+    /// the colour of their last stop, and gradients without stops and degenerate patterns paint
+    /// nothing.
+    pub fn as_solid(&self) -> Option<Color> {
+        match self {
+            Self::Solid(color) => Some(*color),
+            Self::Gradient(gradient) if gradient.stops.is_empty() => Some([0.; 4]),
+            Self::Gradient(gradient) if gradient.is_degenerate() => {
+                gradient.stops.last().map(|stop| stop.color)
+            }
+            Self::Gradient(_) => None,
+            Self::Pattern(pattern) if pattern.is_degenerate() => Some([0.; 4]),
+            Self::Pattern(_) => None,
+        }
+    }
+
+    /// The gradient to paint with, unless this paints the same as a solid colour.
+    pub(crate) fn gradient(&self) -> Option<&Gradient> {
+        match self {
+            Self::Gradient(gradient) if self.as_solid().is_none() => Some(gradient),
+            _ => None,
+        }
+    }
+
+    /// The pattern to paint with, unless this paints the same as a solid colour.
+    pub(crate) fn pattern(&self) -> Option<&Pattern> {
+        match self {
+            Self::Pattern(pattern) if self.as_solid().is_none() => Some(pattern),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops() -> Vec<Stop> {
+        vec![
+            Stop {
+                offset: 0.,
+                color: [1., 0., 0., 1.],
+            },
+            Stop {
+                offset: 1.,
+                color: [0., 0., 1., 1.],
+            },
+        ]
+    }
+
+    #[test]
+    fn position() {
+        let linear = Gradient::linear(Vector2::new(1., 0.), Vector2::new(3., 0.), stops());
+        let radial = Gradient::radial(Vector2::new(0., 0.), 2., stops());
+
+        assert_eq!(linear.position(Vector2::new(2., 5.)), 0.5);
+        assert_eq!(linear.position(Vector2::new(0., 0.)), -0.5);
+        assert_eq!(radial.position(Vector2::new(0., 3.)), 1.5);
+    }
+
+    #[test]
+    fn degenerate_position() {
+        let point = Vector2::new(1., 1.);
+        let linear = Gradient::linear(point, point, stops());
+        let radial = Gradient::radial(point, 0., stops());
+
+        for gradient in &[linear, radial] {
+            assert!(gradient.is_degenerate());
+            assert_eq!(gradient.position(point), 0.);
+            assert_eq!(gradient.position(Vector2::new(2., 3.)), 0.);
+            assert_eq!(
+                Paint::from(gradient.clone()).as_solid(),
+                Some([0., 0., 1., 1.])
+            );
+        }
+    }
+}
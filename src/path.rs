@@ -0,0 +1,928 @@
+//! # Paths
+//!
+//! A `Path` is a list of subpaths, each of which is a connected chain of straight lines and
+//! quadratic beziers. Lines are kept as lines rather than being turned into quadratics with a
+//! collinear control point, so that they can be tessellated without any curve triangles.
+//!
+//! Paths are either built up with a `PathBuilder`, or converted from a polyline or from points in
+//! the implicit format, where the end of one curve is the start of the next but the control
+//! points are not shared:
+//!
+//! [start1, control1, end1/start2, control2, end2/start3, ..]
+//!
+//! Both validate their input. Cubics and arcs are converted to quadratics as they are added, to
+//! within the builder's tolerance. The builder also has helpers for common closed shapes.
+
+use crate::{bounds::Bounds, tessellate::Quadratic};
+use cgmath::{InnerSpace, Vector2};
+use std::{f32::consts::PI, fmt};
+
+/// The default maximum distance between a cubic or arc and the quadratics approximating it, in
+/// the same units as the path. This is well under a pixel when drawing in screen coordinates.
+pub const DEFAULT_TOLERANCE: f32 = 0.001;
+
+/// The maximum angle covered by a single quadratic when approximating an elliptical arc, however
+/// large the tolerance, so that the quadratics stay close to circular. At 45 degrees the error is
+/// about 0.3% of the radius.
+const MAX_ARC_SEGMENT_ANGLE: f32 = PI / 4.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// A segment was added before the first `move_to`.
+    MissingMoveTo,
+    /// A point has a NaN or infinite coordinate.
+    NonFinite(Vector2<f32>),
+    /// Points in the implicit format must be a start point followed by pairs of control and end
+    /// points, so there must be an odd number of at least 3. A polyline needs at least 2 points.
+    /// Contains the number of points given.
+    InvalidPointCount(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMoveTo => write!(f, "Path segment added before the first `move_to`"),
+            Self::NonFinite(p) => write!(f, "Point ({}, {}) is not finite", p.x, p.y),
+            Self::InvalidPointCount(count) => write!(f, "Invalid number of points: {}", count),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn check_finite(p: Vector2<f32>) -> Result<Vector2<f32>, Error> {
+    if p.x.is_finite() && p.y.is_finite() {
+        Ok(p)
+    } else {
+        Err(Error::NonFinite(p))
+    }
+}
+
+/// Rotates `v` by the angle with the given `(sin, cos)`.
+fn rotate((sin, cos): (f32, f32), v: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
+
+fn collect_finite<V>(points: impl IntoIterator<Item = V>) -> Result<Vec<Vector2<f32>>, Error>
+where
+    V: Into<Vector2<f32>>,
+{
+    points.into_iter().map(|p| check_finite(p.into())).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// A straight line as `(start, end)`.
+    Line(Vector2<f32>, Vector2<f32>),
+    Quadratic(Quadratic),
+}
+
+impl Segment {
+    pub fn start(&self) -> Vector2<f32> {
+        match *self {
+            Self::Line(start, _) | Self::Quadratic((start, _, _)) => start,
+        }
+    }
+
+    pub fn end(&self) -> Vector2<f32> {
+        match *self {
+            Self::Line(_, end) | Self::Quadratic((_, _, end)) => end,
+        }
+    }
+
+    fn end_mut(&mut self) -> &mut Vector2<f32> {
+        match self {
+            Self::Line(_, end) | Self::Quadratic((_, _, end)) => end,
+        }
+    }
+
+    /// The point at `t`, from 0 at the start to 1 at the end.
+    pub fn point(&self, t: f32) -> Vector2<f32> {
+        match *self {
+            Self::Line(start, end) => start + (end - start) * t,
+            Self::Quadratic((start, control, end)) => {
+                crate::tessellate::point_on_quadratic(start, control, end, t)
+            }
+        }
+    }
+
+    /// The derivative with respect to `t` at `t`, which points along the segment and has the
+    /// speed at which `point` moves as its magnitude.
+    pub fn derivative(&self, t: f32) -> Vector2<f32> {
+        match *self {
+            Self::Line(start, end) => end - start,
+            Self::Quadratic((start, control, end)) => {
+                2. * (1. - t) * (control - start) + 2. * t * (end - control)
+            }
+        }
+    }
+
+    /// The same segment traced from its end to its start.
+    pub fn reversed(&self) -> Self {
+        match *self {
+            Self::Line(start, end) => Self::Line(end, start),
+            Self::Quadratic((start, control, end)) => Self::Quadratic((end, control, start)),
+        }
+    }
+
+    /// The part of the segment between `from` and `to`, as another segment of the same kind.
+    pub fn slice(&self, from: f32, to: f32) -> Self {
+        match *self {
+            Self::Line(..) => Self::Line(self.point(from), self.point(to)),
+            // The control point of the slice is where the tangents at its ends intersect, which
+            // gives the same result as two rounds of de Casteljau subdivision.
+            Self::Quadratic(_) => {
+                let start = self.point(from);
+
+                Self::Quadratic((
+                    start,
+                    start + self.derivative(from) * (to - from) / 2.,
+                    self.point(to),
+                ))
+            }
+        }
+    }
+
+    /// The smallest box containing the segment.
+    pub fn bounds(&self) -> Bounds {
+        let bounds = Bounds::from_point(self.start()).including(self.end());
+
+        match *self {
+            Self::Line(..) => bounds,
+            Self::Quadratic((start, control, end)) => {
+                // Where the derivative is zero in each axis
+                let denominator = start - 2. * control + end;
+                let extremum = |start: f32, control: f32, denominator: f32| {
+                    Some((start - control) / denominator).filter(|&t| t > 0. && t < 1.)
+                };
+
+                extremum(start.x, control.x, denominator.x)
+                    .into_iter()
+                    .chain(extremum(start.y, control.y, denominator.y))
+                    .fold(bounds, |bounds, t| bounds.including(self.point(t)))
+            }
+        }
+    }
+
+    /// Applies `f` to every point of the segment, including the control point.
+    pub fn map_points(self, mut f: impl FnMut(Vector2<f32>) -> Vector2<f32>) -> Self {
+        match self {
+            Self::Line(start, end) => Self::Line(f(start), f(end)),
+            Self::Quadratic((start, control, end)) => {
+                Self::Quadratic((f(start), f(control), f(end)))
+            }
+        }
+    }
+}
+
+/// A single connected chain of segments, where each segment starts at the end of the last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    segments: Vec<Segment>,
+    closed: bool,
+}
+
+impl Subpath {
+    /// The segments of this subpath. There is always at least one.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn start(&self) -> Vector2<f32> {
+        self.segments[0].start()
+    }
+
+    /// Whether this subpath was ended with `PathBuilder::close`. Closed subpaths always end
+    /// where they start.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    subpaths: Vec<Subpath>,
+}
+
+impl Path {
+    pub fn builder() -> PathBuilder {
+        PathBuilder::new()
+    }
+
+    /// Creates a path with a single open subpath of quadratics from points in the implicit
+    /// format.
+    pub fn from_points<V>(points: impl IntoIterator<Item = V>) -> Result<Self, Error>
+    where
+        V: Into<Vector2<f32>>,
+    {
+        use itertools::Itertools;
+
+        let points = collect_finite(points)?;
+
+        if points.len() < 3 || points.len() % 2 == 0 {
+            return Err(Error::InvalidPointCount(points.len()));
+        }
+
+        Ok(Self::from_segments(
+            points
+                .into_iter()
+                .tuple_windows()
+                .step_by(2)
+                .map(Segment::Quadratic)
+                .collect(),
+        ))
+    }
+
+    /// Creates a path with a single open subpath of straight lines between `points`.
+    pub fn from_polyline<V>(points: impl IntoIterator<Item = V>) -> Result<Self, Error>
+    where
+        V: Into<Vector2<f32>>,
+    {
+        use itertools::Itertools;
+
+        let points = collect_finite(points)?;
+
+        if points.len() < 2 {
+            return Err(Error::InvalidPointCount(points.len()));
+        }
+
+        Ok(Self::from_segments(
+            points
+                .into_iter()
+                .tuple_windows()
+                .map(|(start, end)| Segment::Line(start, end))
+                .collect(),
+        ))
+    }
+
+    fn from_segments(segments: Vec<Segment>) -> Self {
+        Self {
+            subpaths: vec![Subpath {
+                segments,
+                closed: false,
+            }],
+        }
+    }
+
+    pub fn subpaths(&self) -> &[Subpath] {
+        &self.subpaths
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subpaths.is_empty()
+    }
+
+    /// Every segment of every subpath, in order.
+    pub fn segments(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.subpaths
+            .iter()
+            .flat_map(|subpath| subpath.segments.iter().copied())
+    }
+
+    /// The smallest box containing every segment, or `None` if the path is empty.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut bounds = self.segments().map(|segment| segment.bounds());
+        let first = bounds.next()?;
+
+        Some(bounds.fold(first, Bounds::union))
+    }
+
+    /// Applies `f` to every point, including control points. This is exact for affine
+    /// transforms, as beziers are invariant under them.
+    pub fn map_points(&self, mut f: impl FnMut(Vector2<f32>) -> Vector2<f32>) -> Self {
+        Self {
+            subpaths: self
+                .subpaths
+                .iter()
+                .map(|subpath| Subpath {
+                    segments: subpath
+                        .segments
+                        .iter()
+                        .map(|segment| segment.map_points(&mut f))
+                        .collect(),
+                    closed: subpath.closed,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Builds a `Path` one segment at a time. The first error is reported by `build`, so the
+/// drawing methods can be chained.
+///
+/// Subpaths with no segments (for example two `move_to`s in a row) are dropped, as are
+/// zero-length lines and arcs.
+#[derive(Debug, Clone)]
+pub struct PathBuilder {
+    subpaths: Vec<Subpath>,
+    current: Vec<Segment>,
+    start: Option<Vector2<f32>>,
+    tolerance: f32,
+    error: Option<Error>,
+}
+
+impl Default for PathBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathBuilder {
+    pub fn new() -> Self {
+        Self::with_tolerance(DEFAULT_TOLERANCE)
+    }
+
+    /// Creates a builder that approximates cubics and arcs to within `tolerance`, in the same
+    /// units as the path.
+    ///
+    /// # Panics
+    ///
+    /// If `tolerance` is not positive.
+    pub fn with_tolerance(tolerance: f32) -> Self {
+        assert!(
+            tolerance > 0.,
+            "Tolerance must be positive, got {}",
+            tolerance
+        );
+
+        Self {
+            subpaths: Vec::new(),
+            current: Vec::new(),
+            start: None,
+            tolerance,
+            error: None,
+        }
+    }
+
+    /// The end of the last segment, or `None` if nothing has been added yet. After `close` this
+    /// is the start of the subpath that was closed.
+    pub fn position(&self) -> Option<Vector2<f32>> {
+        self.current.last().map(Segment::end).or(self.start)
+    }
+
+    fn fail(&mut self, error: Error) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// The position that the next segment starts at, or `None` (recording the error) if there
+    /// isn't one.
+    fn segment_start(&mut self) -> Option<Vector2<f32>> {
+        let from = self.position();
+
+        if from.is_none() {
+            self.fail(Error::MissingMoveTo);
+        }
+
+        from
+    }
+
+    fn flush(&mut self, closed: bool) {
+        if !self.current.is_empty() {
+            self.subpaths.push(Subpath {
+                segments: std::mem::take(&mut self.current),
+                closed,
+            });
+        }
+    }
+
+    /// Starts a new subpath at `to`.
+    pub fn move_to(&mut self, to: Vector2<f32>) -> &mut Self {
+        if let Err(e) = check_finite(to) {
+            self.fail(e);
+            return self;
+        }
+
+        self.flush(false);
+        self.start = Some(to);
+
+        self
+    }
+
+    pub fn quad_to(&mut self, control: Vector2<f32>, to: Vector2<f32>) -> &mut Self {
+        if let Err(e) = check_finite(control).and_then(|_| check_finite(to)) {
+            self.fail(e);
+            return self;
+        }
+
+        let from = match self.segment_start() {
+            Some(from) => from,
+            None => return self,
+        };
+
+        self.current.push(Segment::Quadratic((from, control, to)));
+
+        self
+    }
+
+    pub fn line_to(&mut self, to: Vector2<f32>) -> &mut Self {
+        if let Err(e) = check_finite(to) {
+            self.fail(e);
+            return self;
+        }
+
+        let from = match self.segment_start() {
+            Some(from) => from,
+            None => return self,
+        };
+
+        if from != to {
+            self.current.push(Segment::Line(from, to));
+        }
+
+        self
+    }
+
+    /// Appends a copy of `segment`, which is assumed to start at the current position.
+    pub(crate) fn segment_to(&mut self, segment: Segment) -> &mut Self {
+        match segment {
+            Segment::Line(_, end) => self.line_to(end),
+            Segment::Quadratic((_, control, end)) => self.quad_to(control, end),
+        }
+    }
+
+    /// Appends a cubic bezier, approximated by as many quadratics as are needed to stay within
+    /// the builder's tolerance.
+    pub fn cubic_to(
+        &mut self,
+        control1: Vector2<f32>,
+        control2: Vector2<f32>,
+        to: Vector2<f32>,
+    ) -> &mut Self {
+        let points = check_finite(control1)
+            .and_then(|_| check_finite(control2))
+            .and_then(|_| check_finite(to));
+
+        if let Err(e) = points {
+            self.fail(e);
+            return self;
+        }
+
+        let from = match self.segment_start() {
+            Some(from) => from,
+            None => return self,
+        };
+
+        // The error of approximating a cubic with a single quadratic is bounded by
+        // `sqrt(3) / 36 * |to - 3 * control2 + 3 * control1 - from|`, and splitting the cubic
+        // into `n` pieces reduces this by a factor of `n^3`.
+        let error = (to - 3. * control2 + 3. * control1 - from).magnitude() * 3f32.sqrt() / 36.;
+        let count = (error / self.tolerance).cbrt().ceil().max(1.) as usize;
+
+        let point = |t: f32| {
+            let mt = 1. - t;
+            from * mt.powi(3)
+                + control1 * 3. * mt.powi(2) * t
+                + control2 * 3. * mt * t.powi(2)
+                + to * t.powi(3)
+        };
+        let derivative = |t: f32| {
+            let mt = 1. - t;
+            (control1 - from) * 3. * mt.powi(2)
+                + (control2 - control1) * 6. * mt * t
+                + (to - control2) * 3. * t.powi(2)
+        };
+
+        let dt = (count as f32).recip();
+
+        for i in 0..count {
+            let (t0, t1) = (i as f32 * dt, (i + 1) as f32 * dt);
+            let (p0, p3) = (point(t0), if i + 1 == count { to } else { point(t1) });
+            let p1 = p0 + derivative(t0) * dt / 3.;
+            let p2 = p3 - derivative(t1) * dt / 3.;
+
+            self.quad_to((3. * (p1 + p2) - p0 - p3) / 4., p3);
+        }
+
+        self
+    }
+
+    /// Appends quadratics approximating an arc of the ellipse with the given centre, radii and
+    /// rotation (as `(sin, cos)` of the angle), starting at `start_angle` and sweeping through
+    /// `sweep_angle` radians. The arc is assumed to start at the current position.
+    fn ellipse_arc(
+        &mut self,
+        centre: Vector2<f32>,
+        radii: Vector2<f32>,
+        rotation: (f32, f32),
+        start_angle: f32,
+        sweep_angle: f32,
+    ) {
+        // A quadratic covering an angle of `2a` of a circle with radius `r` is furthest from it
+        // in the middle, by `r * (1 - cos(a))^2 / (2 * cos(a))`, which is about `r * a^4 / 8`.
+        // Using the larger radius overestimates the error for the rest of an ellipse.
+        let radius = radii.x.max(radii.y);
+        let max_angle = (2. * (8. * self.tolerance / radius).powf(0.25)).min(MAX_ARC_SEGMENT_ANGLE);
+        let count = (sweep_angle.abs() / max_angle).ceil().max(1.) as usize;
+        let step = sweep_angle / count as f32;
+
+        let point = |angle: f32, scale: f32| {
+            let (y, x) = angle.sin_cos();
+
+            centre + rotate(rotation, Vector2::new(x * radii.x, y * radii.y) * scale)
+        };
+
+        for i in 0..count {
+            let from = start_angle + step * i as f32;
+
+            // The control point is where the tangents at either end intersect
+            self.quad_to(
+                point(from + step / 2., (step / 2.).cos().recip()),
+                point(from + step, 1.),
+            );
+        }
+    }
+
+    /// Appends an arc of the ellipse with the given centre and radii, rotated by `x_rotation`
+    /// radians, starting at `start_angle` and sweeping through `sweep_angle` radians. Positive
+    /// angles go from the x axis towards the y axis, and sweeps of more than a full turn are
+    /// clamped to one.
+    ///
+    /// Like `ellipse` on an HTML canvas, this first adds a line from the current position to the
+    /// start of the arc, or starts a new subpath there if nothing has been added yet.
+    pub fn arc(
+        &mut self,
+        centre: Vector2<f32>,
+        radii: Vector2<f32>,
+        x_rotation: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+    ) -> &mut Self {
+        let values = check_finite(centre)
+            .and_then(|_| check_finite(radii))
+            .and_then(|_| check_finite(Vector2::new(start_angle, sweep_angle)))
+            .and_then(|_| check_finite(Vector2::new(x_rotation, 0.)));
+
+        if let Err(e) = values {
+            self.fail(e);
+            return self;
+        }
+
+        let radii = Vector2::new(radii.x.abs(), radii.y.abs());
+        let rotation = x_rotation.sin_cos();
+        let (y, x) = start_angle.sin_cos();
+        let start = centre + rotate(rotation, Vector2::new(x * radii.x, y * radii.y));
+
+        if self.position().is_some() {
+            self.line_to(start);
+        } else {
+            self.move_to(start);
+        }
+
+        if radii.x.max(radii.y) > 0. && sweep_angle != 0. {
+            self.ellipse_arc(
+                centre,
+                radii,
+                rotation,
+                start_angle,
+                sweep_angle.clamp(-2. * PI, 2. * PI),
+            );
+        }
+
+        self
+    }
+
+    /// Appends an SVG-style elliptical arc from the current position to `to`, converting from
+    /// endpoint parameterisation as described in the SVG spec, appendix F.6.5. `x_rotation` is
+    /// in degrees.
+    pub fn arc_to(
+        &mut self,
+        radii: Vector2<f32>,
+        x_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Vector2<f32>,
+    ) -> &mut Self {
+        let from = match self.segment_start() {
+            Some(from) => from,
+            None => return self,
+        };
+
+        if let Err(e) = check_finite(radii).and_then(|_| check_finite(to)) {
+            self.fail(e);
+            return self;
+        }
+
+        if from == to {
+            return self;
+        }
+
+        let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+
+        if rx == 0. || ry == 0. {
+            return self.line_to(to);
+        }
+
+        let (sin, cos) = x_rotation.to_radians().sin_cos();
+        let half = (from - to) / 2.;
+        let p = Vector2::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+
+        // Scale up the radii if they are too small to reach the endpoint
+        let lambda = (p.x / rx).powi(2) + (p.y / ry).powi(2);
+        if lambda > 1. {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = (rx * ry).powi(2) - (rx * p.y).powi(2) - (ry * p.x).powi(2);
+        let denominator = (rx * p.y).powi(2) + (ry * p.x).powi(2);
+        let coefficient =
+            (numerator / denominator).max(0.).sqrt() * if large_arc == sweep { -1. } else { 1. };
+        let centre_prime = Vector2::new(rx * p.y / ry, -ry * p.x / rx) * coefficient;
+        let centre = rotate((sin, cos), centre_prime) + (from + to) / 2.;
+
+        let start = Vector2::new((p.x - centre_prime.x) / rx, (p.y - centre_prime.y) / ry);
+        let end = Vector2::new((-p.x - centre_prime.x) / rx, (-p.y - centre_prime.y) / ry);
+
+        let start_angle = start.y.atan2(start.x);
+        let mut sweep_angle = (start.x * end.y - start.y * end.x).atan2(start.dot(end));
+
+        if !sweep && sweep_angle > 0. {
+            sweep_angle -= 2. * PI;
+        } else if sweep && sweep_angle < 0. {
+            sweep_angle += 2. * PI;
+        }
+
+        self.ellipse_arc(
+            centre,
+            Vector2::new(rx, ry),
+            (sin, cos),
+            start_angle,
+            sweep_angle,
+        );
+
+        // Avoid accumulating error in the endpoint
+        if let Some(last) = self.current.last_mut() {
+            *last.end_mut() = to;
+        }
+
+        self
+    }
+
+    /// Ends the current subpath with a line back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        if let Some(start) = self.start {
+            self.line_to(start);
+            self.flush(true);
+        }
+
+        self
+    }
+
+    /// Appends a closed, axis-aligned rectangle as a new subpath. Nothing is added if either
+    /// side is not positive.
+    pub fn rect(&mut self, origin: Vector2<f32>, size: Vector2<f32>) -> &mut Self {
+        self.rounded_rect(origin, size, Vector2::new(0., 0.))
+    }
+
+    /// Appends a closed, axis-aligned rectangle with its corners rounded to quarters of an
+    /// ellipse with the given radii, as a new subpath. The radii are reduced to half of the size
+    /// if they are larger, and the corners are square if either radius is not positive.
+    pub fn rounded_rect(
+        &mut self,
+        origin: Vector2<f32>,
+        size: Vector2<f32>,
+        radii: Vector2<f32>,
+    ) -> &mut Self {
+        let values = check_finite(origin)
+            .and_then(|_| check_finite(size))
+            .and_then(|_| check_finite(radii));
+
+        if let Err(e) = values {
+            self.fail(e);
+            return self;
+        }
+
+        if size.x <= 0. || size.y <= 0. {
+            return self;
+        }
+
+        if radii.x <= 0. || radii.y <= 0. {
+            return self
+                .move_to(origin)
+                .line_to(origin + Vector2::new(size.x, 0.))
+                .line_to(origin + size)
+                .line_to(origin + Vector2::new(0., size.y))
+                .close();
+        }
+
+        let radii = Vector2::new(radii.x.min(size.x / 2.), radii.y.min(size.y / 2.));
+        let (min, max) = (origin + radii, origin + size - radii);
+        let no_rotation = (0., 1.);
+
+        self.move_to(Vector2::new(min.x, origin.y));
+        self.line_to(Vector2::new(max.x, origin.y));
+        self.ellipse_arc(
+            Vector2::new(max.x, min.y),
+            radii,
+            no_rotation,
+            -PI / 2.,
+            PI / 2.,
+        );
+        self.line_to(Vector2::new(origin.x + size.x, max.y));
+        self.ellipse_arc(max, radii, no_rotation, 0., PI / 2.);
+        self.line_to(Vector2::new(min.x, origin.y + size.y));
+        self.ellipse_arc(
+            Vector2::new(min.x, max.y),
+            radii,
+            no_rotation,
+            PI / 2.,
+            PI / 2.,
+        );
+        self.line_to(Vector2::new(origin.x, min.y));
+        self.ellipse_arc(min, radii, no_rotation, PI, PI / 2.);
+        self.close()
+    }
+
+    /// Appends a closed, axis-aligned ellipse as a new subpath. Nothing is added if either radius
+    /// is not positive.
+    pub fn ellipse(&mut self, centre: Vector2<f32>, radii: Vector2<f32>) -> &mut Self {
+        if let Err(e) = check_finite(centre).and_then(|_| check_finite(radii)) {
+            self.fail(e);
+            return self;
+        }
+
+        if radii.x <= 0. || radii.y <= 0. {
+            return self;
+        }
+
+        self.move_to(centre + Vector2::new(radii.x, 0.));
+        self.ellipse_arc(centre, radii, (0., 1.), 0., 2. * PI);
+        self.close()
+    }
+
+    /// Appends a closed circle as a new subpath. Nothing is added if the radius is not positive.
+    pub fn circle(&mut self, centre: Vector2<f32>, radius: f32) -> &mut Self {
+        self.ellipse(centre, Vector2::new(radius, radius))
+    }
+
+    pub fn build(mut self) -> Result<Path, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        self.flush(false);
+
+        Ok(Path {
+            subpaths: self.subpaths,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quadratic(start: [f32; 2], control: [f32; 2], end: [f32; 2]) -> Segment {
+        Segment::Quadratic((start.into(), control.into(), end.into()))
+    }
+
+    fn assert_bounds(segment: Segment, min: [f32; 2], max: [f32; 2]) {
+        let bounds = segment.bounds();
+        let near =
+            |a: Vector2<f32>, b: [f32; 2]| (a.x - b[0]).abs() < 1e-6 && (a.y - b[1]).abs() < 1e-6;
+
+        assert!(
+            near(bounds.min, min) && near(bounds.max, max),
+            "{:?}",
+            bounds
+        );
+    }
+
+    #[test]
+    fn line_bounds() {
+        assert_bounds(
+            Segment::Line(Vector2::new(2., -1.), Vector2::new(-1., 3.)),
+            [-1., -1.],
+            [2., 3.],
+        );
+    }
+
+    #[test]
+    fn quadratic_bounds_extrema() {
+        // Monotonic, so only the ends count
+        assert_bounds(quadratic([0., 0.], [1., 1.], [2., 4.]), [0., 0.], [2., 4.]);
+        // An extremum in y at `t` = 0.5, halfway to the control point
+        assert_bounds(quadratic([0., 0.], [1., 2.], [2., 0.]), [0., 0.], [2., 1.]);
+        assert_bounds(
+            quadratic([0., 0.], [1., -2.], [2., 0.]),
+            [0., -1.],
+            [2., 0.],
+        );
+        // An extremum in x at `t` = 0.5
+        assert_bounds(quadratic([0., 0.], [2., 1.], [0., 2.]), [0., 0.], [1., 2.]);
+        // Extrema in x at `t` = 1/2 and in y at `t` = 2/3
+        assert_bounds(
+            quadratic([0., 0.], [2., 2.], [0., 1.]),
+            [0., 0.],
+            [1., 4. / 3.],
+        );
+    }
+
+    #[test]
+    fn path_bounds() {
+        let mut builder = PathBuilder::new();
+        builder
+            .move_to(Vector2::new(0., 0.))
+            .quad_to(Vector2::new(1., 2.), Vector2::new(2., 0.))
+            .move_to(Vector2::new(-1., -1.))
+            .line_to(Vector2::new(-2., -2.));
+        let bounds = builder.build().unwrap().bounds().unwrap();
+
+        assert_eq!(bounds.min, Vector2::new(-2., -2.));
+        assert_eq!(bounds.max, Vector2::new(2., 1.));
+        assert_eq!(Path::default().bounds(), None);
+    }
+
+    #[test]
+    fn from_points_count() {
+        let points = |count: usize| (0..count).map(|i| [i as f32, 0.]);
+
+        assert_eq!(
+            Path::from_points(points(0)),
+            Err(Error::InvalidPointCount(0))
+        );
+        assert_eq!(
+            Path::from_points(points(1)),
+            Err(Error::InvalidPointCount(1))
+        );
+        assert_eq!(
+            Path::from_points(points(4)),
+            Err(Error::InvalidPointCount(4))
+        );
+        assert_eq!(Path::from_points(points(5)).unwrap().segments().count(), 2);
+
+        assert_eq!(
+            Path::from_polyline(points(1)),
+            Err(Error::InvalidPointCount(1))
+        );
+        assert_eq!(
+            Path::from_polyline(points(2)).unwrap().segments().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn from_points_non_finite() {
+        // NaN is never equal to itself, so the points can't be compared with `assert_eq`
+        assert!(matches!(
+            Path::from_points(vec![[0., 0.], [f32::NAN, 1.], [2., 0.]]),
+            Err(Error::NonFinite(p)) if p.x.is_nan() && p.y == 1.
+        ));
+        assert!(matches!(
+            Path::from_polyline(vec![[0., 0.], [1., f32::INFINITY]]),
+            Err(Error::NonFinite(p)) if p.y == f32::INFINITY
+        ));
+    }
+
+    #[test]
+    fn builder_missing_move_to() {
+        let mut builder = Path::builder();
+        builder.line_to(Vector2::new(1., 0.));
+
+        assert_eq!(builder.build(), Err(Error::MissingMoveTo));
+
+        let mut builder = Path::builder();
+        builder.quad_to(Vector2::new(1., 1.), Vector2::new(2., 0.));
+
+        assert_eq!(builder.build(), Err(Error::MissingMoveTo));
+    }
+
+    #[test]
+    fn builder_non_finite() {
+        let mut builder = Path::builder();
+        builder
+            .move_to(Vector2::new(0., 0.))
+            .quad_to(Vector2::new(1., f32::NEG_INFINITY), Vector2::new(2., 0.));
+
+        assert!(matches!(
+            builder.build(),
+            Err(Error::NonFinite(p)) if p.y == f32::NEG_INFINITY
+        ));
+
+        let mut builder = Path::builder();
+        builder.move_to(Vector2::new(f32::NAN, 0.));
+
+        assert!(matches!(builder.build(), Err(Error::NonFinite(p)) if p.x.is_nan()));
+    }
+
+    #[test]
+    fn builder_keeps_first_error() {
+        let mut builder = Path::builder();
+        builder
+            .line_to(Vector2::new(1., 0.))
+            .move_to(Vector2::new(f32::NAN, 0.))
+            .move_to(Vector2::new(0., 0.))
+            .line_to(Vector2::new(1., 1.));
+
+        assert_eq!(builder.build(), Err(Error::MissingMoveTo));
+
+        let mut builder = Path::builder();
+        builder
+            .move_to(Vector2::new(0., f32::INFINITY))
+            .line_to(Vector2::new(1., 0.));
+
+        assert!(matches!(builder.build(), Err(Error::NonFinite(_))));
+    }
+}
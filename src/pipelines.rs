@@ -48,10 +48,12 @@ impl BindId {
 
 const WINDING_MODE: wgpu::FrontFace = wgpu::FrontFace::Ccw;
 
-pub const SWAPCHAIN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
-
 /// This is responsible for rendering the curves themselves. The XOR behaviour of overlapping
 /// SVGs are handled via the `blit` pass.
+///
+/// The shader writes the same value to every channel, and the pipeline only lets it through to
+/// one of them, so that up to `blit::INTERMEDIATE_CHANNELS` draws can share the intermediate
+/// buffer without their coverage adding up.
 pub mod curve {
     use super::ShaderModuleSourceExt;
     use lazy_static::lazy_static;
@@ -63,7 +65,7 @@ pub mod curve {
     use memoffset::offset_of;
     use std::mem;
 
-    #[derive(Copy, Clone, PartialEq)]
+    #[derive(Copy, Clone, Debug, PartialEq)]
     pub struct Vertex {
         pub pos: [f32; 2],
         pub uv: [f32; 2],
@@ -80,23 +82,56 @@ pub mod curve {
             wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/curve.frag.spv"));
     }
 
-    const COLOR_STATES: &[wgpu::ColorStateDescriptor] = &[wgpu::ColorStateDescriptor {
-        format: super::blit::INTERMEDIATE_TEXTURE_FORMAT,
-        color_blend: wgpu::BlendDescriptor::REPLACE,
-        alpha_blend: wgpu::BlendDescriptor {
-            src_factor: wgpu::BlendFactor::One,
-            dst_factor: wgpu::BlendFactor::One,
-            operation: wgpu::BlendOperation::Add,
-        },
-        write_mask: wgpu::ColorWrite::ALL,
-    }];
-
-    pub fn build(device: &wgpu::Device) -> Pipeline {
+    const ADD: wgpu::BlendDescriptor = wgpu::BlendDescriptor {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+
+    /// Accumulates into `channel` of the intermediate buffer, leaving the others to the draws
+    /// that share the pass.
+    fn color_states(channel: u32) -> [wgpu::ColorStateDescriptor; 1] {
+        let write_mask = [
+            wgpu::ColorWrite::RED,
+            wgpu::ColorWrite::GREEN,
+            wgpu::ColorWrite::BLUE,
+            wgpu::ColorWrite::ALPHA,
+        ][channel as usize];
+
+        [wgpu::ColorStateDescriptor {
+            format: super::blit::INTERMEDIATE_TEXTURE_FORMAT,
+            color_blend: ADD,
+            alpha_blend: ADD,
+            write_mask,
+        }]
+    }
+
+    /// Builds the pipeline for an intermediate buffer with `sample_count` samples per pixel,
+    /// drawing into `channel`.
+    pub fn build(device: &wgpu::Device, sample_count: u32, channel: u32) -> Pipeline {
+        build_with_fragment(
+            device,
+            "pipeline_layout_curve",
+            &FRAGMENT_SHADER,
+            sample_count,
+            channel,
+        )
+    }
+
+    /// Builds a pipeline that takes the same vertices as the curve pipeline, but with a
+    /// different fragment shader.
+    pub(super) fn build_with_fragment(
+        device: &wgpu::Device,
+        label: &str,
+        fragment_shader: &wgpu::ShaderModuleSource<'static>,
+        sample_count: u32,
+        channel: u32,
+    ) -> Pipeline {
         let vs_module = device.create_shader_module(VERTEX_SHADER.as_ref());
-        let fs_module = device.create_shader_module(FRAGMENT_SHADER.as_ref());
+        let fs_module = device.create_shader_module(fragment_shader.as_ref());
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("pipeline_layout_curve"),
+            label: Some(label),
             bind_group_layouts: &[],
             push_constant_ranges: &[],
         });
@@ -123,7 +158,7 @@ pub mod curve {
                 clamp_depth: false,
             }),
             primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: COLOR_STATES,
+            color_states: &color_states(channel),
             depth_stencil_state: None,
             vertex_state: wgpu::VertexStateDescriptor {
                 index_format: wgpu::IndexFormat::Uint16,
@@ -149,7 +184,7 @@ pub mod curve {
                     ],
                 }],
             },
-            sample_count: 1,
+            sample_count,
             sample_mask: !0,
             alpha_to_coverage_enabled: false,
         });
@@ -161,6 +196,35 @@ pub mod curve {
     }
 }
 
+/// This fills the inside of closed paths. It takes the same vertices as the `curve` pipeline, but
+/// instead of coverage it accumulates the winding number of each pixel, in a single channel of
+/// the intermediate buffer like the `curve` pipeline. Fan triangles have `sign == 0` and cover
+/// their whole area, whereas triangles with `sign != 0` only cover the area between a quadratic
+/// and its chord. Each fragment adds or subtracts 1 depending on which way the triangle faces,
+/// and the fill rule is applied in the `blit` pass.
+pub mod fill {
+    use lazy_static::lazy_static;
+
+    pub use super::curve::Pipeline;
+
+    lazy_static! {
+        static ref FRAGMENT_SHADER: wgpu::ShaderModuleSource<'static> =
+            wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/fill.frag.spv"));
+    }
+
+    /// Builds the pipeline for an intermediate buffer with `sample_count` samples per pixel,
+    /// drawing into `channel`.
+    pub fn build(device: &wgpu::Device, sample_count: u32, channel: u32) -> Pipeline {
+        super::curve::build_with_fragment(
+            device,
+            "pipeline_layout_fill",
+            &FRAGMENT_SHADER,
+            sample_count,
+            channel,
+        )
+    }
+}
+
 /// This renders from the intermediate buffer into the output swapchain. In the curve pass, the unrendered
 /// parts of the curve are rendered with negative alpha. This means that for the blit shader to correctly
 /// render the generated texture, sRGBA is required so that negative alpha can be expressed (technically
@@ -168,9 +232,32 @@ pub mod curve {
 /// the shapes). We use floats here because it makes testing easier, but this could probably even be done
 /// with `Rgba8Sint` or `Rgba8Snorm`.
 ///
-/// This should probably also be where we handle FXAA.
+/// Each path is drawn into one channel of the intermediate buffer and then blitted, and the
+/// colour, the channel and the way to interpret it are passed per-vertex so that the blit
+/// vertices can be batched into a single buffer, and the paths sharing the intermediate buffer
+/// can be blitted in a single pass.
+///
+/// When the intermediate buffer is multisampled, this pass also resolves it. The hardware resolve
+/// can't be used, as it averages the alpha of the samples before the blit converts it to
+/// coverage, so `blit_msaa.frag` converts each sample and averages the coverage instead.
+///
+/// Without multisampling, `blit_fxaa.frag` can smooth the edges of each path with FXAA instead.
+/// This runs on the coverage of the one path being blitted rather than on the finished image, so
+/// it only finds the edges of that path and never blurs across paths that overlap it.
+///
+/// Gradients are painted by the same fragment shaders as solid colours, which choose between
+/// them by `PaintKind`, rather than by a pipeline of their own. The kind is the same for every
+/// vertex of a path, so the branch never diverges within one, and the paths that share the
+/// intermediate buffer can still be blitted with one draw however they are painted. A separate
+/// variant would also be needed for each of the three fragment shaders above.
+///
+/// Patterns are painted here for the same reasons, rather than by a `curve` pipeline with the
+/// image bound. The `curve` and `fill` pipelines only accumulate coverage or a winding number
+/// into one channel, and colour can only be applied once the fill rule, the resolve and FXAA
+/// have turned that into coverage.
 pub mod blit {
     use super::ShaderModuleSourceExt;
+    use crate::blend::BlendMode;
     use lazy_static::lazy_static;
 
     use super::BindId;
@@ -181,21 +268,110 @@ pub mod blit {
 
     pub const INTERMEDIATE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
-    const COLOR_STATES: &[wgpu::ColorStateDescriptor] = &[wgpu::ColorStateDescriptor {
-        format: super::SWAPCHAIN_FORMAT,
-        color_blend: wgpu::BlendDescriptor {
-            src_factor: wgpu::BlendFactor::SrcAlpha,
-            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-            operation: wgpu::BlendOperation::Add,
-        },
-        alpha_blend: wgpu::BlendDescriptor::REPLACE,
-        write_mask: wgpu::ColorWrite::ALL,
-    }];
+    /// The number of channels of the intermediate buffer, and so the number of draws that can
+    /// share it.
+    pub const INTERMEDIATE_CHANNELS: u32 = 4;
+
+    /// The format of the texture holding the ramps of gradients, one per row. The shader
+    /// interpolates between texels itself, as 32-bit float textures can't be filtered everywhere.
+    pub const RAMP_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+    /// The format of the texture that the images of patterns are packed into. Each image has to
+    /// wrap within its own region of the texture, which the sampler's address modes can't do, so
+    /// the shader filters it by hand and hardware filtering would go unused. The texels are
+    /// premultiplied after being decoded by `ColorSpace::premultiply`, which an sRGB format
+    /// can't store and 8 bits per channel would band, and are the same values as the CPU
+    /// rasterizer samples. This costs 16 bytes per pixel.
+    pub const IMAGE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+    /// The textures that paints other than solid colours are read from.
+    pub struct PaintTextures<'a> {
+        pub ramps: &'a wgpu::TextureView,
+        pub images: &'a wgpu::TextureView,
+    }
+
+    /// The render target that a blit pipeline draws onto.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct Target {
+        /// From `ColorSpace::format`.
+        pub format: wgpu::TextureFormat,
+        pub blend: BlendMode,
+    }
+
+    /// Blending of premultiplied colour onto the render target.
+    fn color_states(target: Target) -> [wgpu::ColorStateDescriptor; 1] {
+        let [(color_src, color_dst), (alpha_src, alpha_dst)] = target.blend.factors();
+
+        [wgpu::ColorStateDescriptor {
+            format: target.format,
+            color_blend: wgpu::BlendDescriptor {
+                src_factor: color_src,
+                dst_factor: color_dst,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha_blend: wgpu::BlendDescriptor {
+                src_factor: alpha_src,
+                dst_factor: alpha_dst,
+                operation: wgpu::BlendOperation::Add,
+            },
+            write_mask: wgpu::ColorWrite::ALL,
+        }]
+    }
+
+    /// How a channel of the intermediate buffer is converted to coverage. The values must match
+    /// the `MODE_*` definitions in `blit.glsl`.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(u32)]
+    pub enum Mode {
+        /// The output of the `curve` pipeline, where negative alpha is clamped to zero.
+        Stroke = 0,
+        /// The output of the `fill` pipeline with the nonzero fill rule.
+        NonZero = 1,
+        /// The output of the `fill` pipeline with the even-odd fill rule.
+        EvenOdd = 2,
+    }
+
+    /// What a path is painted with. The values must match the `PAINT_*` definitions in
+    /// `blit.glsl`.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(u32)]
+    pub enum PaintKind {
+        /// `Vertex::color`.
+        Solid = 0,
+        /// A linear gradient from `Vertex::gradient[0..2]` to `Vertex::gradient[2..4]`.
+        Linear = 1,
+        /// A radial gradient around `Vertex::gradient[0..2]` with radius `Vertex::gradient[2]`.
+        Radial = 2,
+        /// The image at `Vertex::image` in the image texture.
+        Pattern = 3,
+    }
 
     #[derive(Copy, Clone, PartialEq)]
     pub struct Vertex {
         pub pos: [f32; 2],
         pub uv: [f32; 2],
+        /// Premultiplied, from `ColorSpace::premultiply`.
+        pub color: [f32; 4],
+        pub mode: u32,
+        /// The channel of the intermediate buffer holding the path, from 0 to
+        /// `INTERMEDIATE_CHANNELS`.
+        pub channel: u32,
+        pub paint: u32,
+        /// The `Spread` of a gradient or pattern.
+        pub spread: u32,
+        /// The points of a gradient in screen coordinates, as described by `PaintKind`.
+        pub gradient: [f32; 4],
+        /// The row of the ramp texture holding the colours of a gradient.
+        pub ramp: u32,
+        /// The rows of the affine transformation from screen coordinates to the pixels of the
+        /// image of a pattern.
+        pub pattern_x: [f32; 3],
+        pub pattern_y: [f32; 3],
+        /// The region of the image texture holding the image of a pattern, from
+        /// `cache::TextureCache`.
+        pub image: [u32; 4],
+        /// The `Filter` of a pattern.
+        pub filter: u32,
     }
 
     unsafe impl bytemuck::Pod for Vertex {}
@@ -206,15 +382,35 @@ pub mod blit {
             wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/blit.vert.spv"));
         static ref FRAGMENT_SHADER: wgpu::ShaderModuleSource<'static> =
             wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/blit.frag.spv"));
+        static ref MSAA_FRAGMENT_SHADER: wgpu::ShaderModuleSource<'static> =
+            wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/blit_msaa.frag.spv"));
+        static ref FXAA_FRAGMENT_SHADER: wgpu::ShaderModuleSource<'static> =
+            wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/blit_fxaa.frag.spv"));
     }
 
+    /// Builds the pipeline that blits from `intermediate_texture`, which has `sample_count`
+    /// samples per pixel, onto `target`. `fxaa` is ignored if there is more than one sample per
+    /// pixel.
     pub fn build(
         device: &wgpu::Device,
         sampler: &wgpu::Sampler,
         intermediate_texture: &wgpu::TextureView,
+        paint_textures: &PaintTextures,
+        sample_count: u32,
+        fxaa: bool,
+        target: Target,
     ) -> Pipeline {
+        let multisampled = sample_count > 1;
+        let fragment_shader = if multisampled {
+            &*MSAA_FRAGMENT_SHADER
+        } else if fxaa {
+            &*FXAA_FRAGMENT_SHADER
+        } else {
+            &*FRAGMENT_SHADER
+        };
+
         let vs_module = device.create_shader_module(VERTEX_SHADER.as_ref());
-        let fs_module = device.create_shader_module(FRAGMENT_SHADER.as_ref());
+        let fs_module = device.create_shader_module(fragment_shader.as_ref());
 
         let mut ids = BindId::default();
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -226,6 +422,26 @@ pub mod blit {
                     ty: wgpu::BindingType::Sampler { comparison: false },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: ids.next(),
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: ids.next(),
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
                 wgpu::BindGroupLayoutEntry {
                     binding: ids.next(),
                     visibility: wgpu::ShaderStage::FRAGMENT,
@@ -266,7 +482,7 @@ pub mod blit {
                 clamp_depth: false,
             }),
             primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: COLOR_STATES,
+            color_states: &color_states(target),
             depth_stencil_state: None,
             vertex_state: wgpu::VertexStateDescriptor {
                 index_format: wgpu::IndexFormat::Uint16,
@@ -284,6 +500,61 @@ pub mod blit {
                             offset: offset_of!(Vertex, uv) as u64,
                             shader_location: ids.next(),
                         },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: offset_of!(Vertex, color) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, mode) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, paint) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, spread) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: offset_of!(Vertex, gradient) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, ramp) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float3,
+                            offset: offset_of!(Vertex, pattern_x) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float3,
+                            offset: offset_of!(Vertex, pattern_y) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint4,
+                            offset: offset_of!(Vertex, image) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, filter) as u64,
+                            shader_location: ids.next(),
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Uint,
+                            offset: offset_of!(Vertex, channel) as u64,
+                            shader_location: ids.next(),
+                        },
                     ],
                 }],
             },
@@ -306,6 +577,14 @@ pub mod blit {
                     binding: ids.next(),
                     resource: wgpu::BindingResource::TextureView(intermediate_texture),
                 },
+                wgpu::BindGroupEntry {
+                    binding: ids.next(),
+                    resource: wgpu::BindingResource::TextureView(paint_textures.ramps),
+                },
+                wgpu::BindGroupEntry {
+                    binding: ids.next(),
+                    resource: wgpu::BindingResource::TextureView(paint_textures.images),
+                },
             ],
         });
 
@@ -0,0 +1,480 @@
+//! # CPU rasterizer
+//!
+//! A pure-Rust reference implementation of the `curve`, `fill` and `blit` passes. It draws
+//! exactly the same vertices as `Canvas`, samples each pixel at its centre (or at the standard
+//! sample positions when multisampling) like the GPU does, and reproduces the additive
+//! accumulation in the intermediate buffer (including negative alpha), the conversion of that
+//! alpha to coverage in the blit pass, including FXAA, and the blending of each blend mode. This
+//! lets us check the output of the tessellator without a GPU, and render when no adapter is
+//! available at all.
+//!
+//! The fragment functions below must be kept in sync with the shaders of the same name.
+
+use crate::{
+    headless::RgbaImage,
+    paint::{sample_image, sample_ramp},
+    path::Path,
+    pipelines::{blit, curve},
+    renderer::{self, Draw, DrawKind, InvalidTessellation, Options, SampleCount},
+    scene::Scene,
+};
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector2};
+
+/// The positions of the samples within a pixel for each sample count, from its top left corner.
+/// These are the standard positions in Vulkan, D3D and Metal.
+fn sample_positions(sample_count: SampleCount) -> &'static [[f32; 2]] {
+    match sample_count {
+        SampleCount::One => &[[0.5, 0.5]],
+        SampleCount::Four => &[
+            [0.375, 0.125],
+            [0.875, 0.375],
+            [0.125, 0.625],
+            [0.625, 0.875],
+        ],
+        SampleCount::Eight => &[
+            [0.5625, 0.3125],
+            [0.4375, 0.6875],
+            [0.8125, 0.5625],
+            [0.3125, 0.1875],
+            [0.1875, 0.8125],
+            [0.0625, 0.4375],
+            [0.6875, 0.9375],
+            [0.9375, 0.0625],
+        ],
+    }
+}
+
+pub struct SoftwareCanvas {
+    size: (u32, u32),
+    /// The alpha channel of the intermediate buffer, with every sample of a pixel next to each
+    /// other. The colour channels are always white.
+    intermediate: Vec<f32>,
+    /// The premultiplied colour of the render target, as it is blended rather than as it is
+    /// stored, so in linear light for an sRGB target.
+    target: Vec<[f32; 4]>,
+}
+
+impl SoftwareCanvas {
+    pub fn new((width, height): (u32, u32)) -> Self {
+        let len = (width * height) as usize;
+
+        Self {
+            size: (width, height),
+            intermediate: vec![0.; len],
+            target: vec![[0.; 4]; len],
+        }
+    }
+
+    /// The software equivalent of `Canvas::render`.
+    pub fn render(
+        &mut self,
+        path: &Path,
+        options: &Options,
+    ) -> Result<RgbaImage, InvalidTessellation> {
+        let draws = renderer::path_draws(path, options, self.size)?;
+
+        Ok(self.draw(draws, options))
+    }
+
+    /// The software equivalent of `Canvas::render_scene`.
+    pub fn render_scene(
+        &mut self,
+        scene: &Scene,
+        options: &Options,
+    ) -> Result<RgbaImage, InvalidTessellation> {
+        let draws = renderer::scene_draws(scene, options, self.size)?;
+
+        Ok(self.draw(draws, options))
+    }
+
+    fn draw(&mut self, draws: Vec<Draw>, options: &Options) -> RgbaImage {
+        let (width, height) = self.size;
+        let samples = sample_positions(options.sample_count);
+
+        self.intermediate
+            .resize((width * height) as usize * samples.len(), 0.);
+
+        let color_space = options.color_space;
+        // The target is rounded to 8 bits per channel in the colour space it is stored in
+        let quantize = |c: f32| (c.clamp(0., 1.) * 255.).round() / 255.;
+        let quantize_color = |c: f32| color_space.decode(quantize(color_space.encode(c)));
+        let quantize_pixel = |[r, g, b, a]: [f32; 4]| {
+            [
+                quantize_color(r),
+                quantize_color(g),
+                quantize_color(b),
+                quantize(a),
+            ]
+        };
+
+        let clear = quantize_pixel(color_space.premultiply(options.clear_color));
+
+        for pixel in &mut self.target {
+            *pixel = clear;
+        }
+
+        for draw in draws.into_iter().filter(|draw| !draw.vertices.is_empty()) {
+            for alpha in &mut self.intermediate {
+                *alpha = 0.;
+            }
+
+            for triangle in draw.vertices.chunks_exact(3) {
+                let intermediate = &mut self.intermediate;
+
+                rasterize(
+                    self.size,
+                    samples,
+                    [triangle[0], triangle[1], triangle[2]],
+                    |index, fragment| {
+                        intermediate[index] += match draw.kind {
+                            DrawKind::Stroke => curve_fragment(fragment),
+                            DrawKind::Fill(_) => fill_fragment(fragment),
+                        };
+                    },
+                );
+            }
+
+            let mode = draw.kind.blit_mode();
+
+            // `blit_msaa.frag` converts each sample to coverage before averaging them
+            let mut coverage = self
+                .intermediate
+                .chunks_exact(samples.len())
+                .map(|pixel| {
+                    pixel
+                        .iter()
+                        .map(|&alpha| blit_coverage(alpha, mode))
+                        .sum::<f32>()
+                        / samples.len() as f32
+                })
+                .collect::<Vec<_>>();
+
+            if options.fxaa && samples.len() == 1 {
+                coverage = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| (x, y)))
+                    .map(|pixel| fxaa(&coverage, self.size, pixel))
+                    .collect();
+            }
+
+            let solid = draw
+                .paint
+                .as_solid()
+                .map(|color| color_space.premultiply(color));
+            let ramp = draw
+                .paint
+                .gradient()
+                .map(|gradient| (gradient, gradient.ramp(color_space)));
+            let image = draw.paint.pattern().map(|pattern| {
+                let inverse = pattern.transform.invert().unwrap_or_else(Matrix3::identity);
+
+                (pattern, inverse, pattern.image.texels(color_space))
+            });
+
+            for (index, (dst, &coverage)) in self.target.iter_mut().zip(&coverage).enumerate() {
+                let (x, y) = (index as u32 % width, index as u32 / width);
+                // The centre of the pixel in screen coordinates, as in `blit.vert`
+                let position = Vector2::new(
+                    (x as f32 + 0.5) / width as f32 * 2. - 1.,
+                    1. - (y as f32 + 0.5) / height as f32 * 2.,
+                );
+
+                let color = if let Some((gradient, ramp)) = &ramp {
+                    sample_ramp(ramp, gradient.spread.apply(gradient.position(position)))
+                } else if let Some((pattern, inverse, texels)) = &image {
+                    sample_image(
+                        texels,
+                        (pattern.image.width, pattern.image.height),
+                        pattern.spread,
+                        pattern.filter,
+                        (inverse * position.extend(1.)).truncate(),
+                    )
+                } else {
+                    solid.unwrap_or([0.; 4])
+                };
+
+                let src = |i: usize| color[i] * coverage;
+
+                *dst = quantize_pixel(draw.blend.blend([src(0), src(1), src(2), src(3)], *dst));
+            }
+        }
+
+        RgbaImage::from_premultiplied(
+            width,
+            height,
+            self.target.iter().map(|pixel| {
+                let byte = |c: f32| (c * 255.).round() as u8;
+                let color_byte = |c: f32| byte(color_space.encode(c));
+
+                [
+                    color_byte(pixel[0]),
+                    color_byte(pixel[1]),
+                    color_byte(pixel[2]),
+                    byte(pixel[3]),
+                ]
+            }),
+            color_space,
+        )
+    }
+}
+
+/// The inputs to the fragment shaders, along with the screen-space derivatives of `uv` that
+/// `dFdx` and `dFdy` give, in pixels.
+#[derive(Debug, Copy, Clone)]
+struct Fragment {
+    uv: Vector2<f32>,
+    du: Vector2<f32>,
+    dv: Vector2<f32>,
+    sign: f32,
+    front_facing: bool,
+}
+
+/// `MIN_GRADIENT` in `curve.frag` and `fill.frag`
+const MIN_GRADIENT: f32 = 1e-6;
+
+/// `coverage` in `curve.frag`
+fn edge_coverage(distance: f32) -> f32 {
+    (1. - distance).clamp(0., 1.)
+}
+
+/// `curve.frag`
+fn curve_fragment(fragment: Fragment) -> f32 {
+    let Fragment {
+        uv, du, dv, sign, ..
+    } = fragment;
+
+    if sign == 0. {
+        edge_coverage((uv.y.abs() - 1.) / dv.magnitude().max(MIN_GRADIENT))
+    } else {
+        let implicit = uv.x * uv.x - uv.y;
+        let gradient = 2. * uv.x * du - dv;
+        let inside = edge_coverage(sign * implicit / gradient.magnitude().max(MIN_GRADIENT));
+        let in_triangle = uv.y >= 0. && uv.y >= 2. * uv.x - 1. && uv.y <= uv.x;
+        let in_range = uv.x >= 0. && uv.x <= 1.;
+
+        if in_triangle {
+            if inside > 0. {
+                inside
+            } else {
+                -2.
+            }
+        } else if in_range {
+            inside
+        } else {
+            0.
+        }
+    }
+}
+
+/// `fill.frag`
+fn fill_fragment(fragment: Fragment) -> f32 {
+    let Fragment {
+        uv,
+        du,
+        dv,
+        sign,
+        front_facing,
+    } = fragment;
+    let inside = if sign == 0. {
+        1.
+    } else {
+        let implicit = uv.x * uv.x - uv.y;
+        let gradient = 2. * uv.x * du - dv;
+
+        (0.5 - implicit / gradient.magnitude().max(MIN_GRADIENT)).clamp(0., 1.)
+    };
+    let winding = if front_facing { 1. } else { -1. };
+
+    winding * inside
+}
+
+/// `coverage` in `blit.glsl`
+fn blit_coverage(alpha: f32, mode: blit::Mode) -> f32 {
+    match mode {
+        blit::Mode::NonZero => alpha.abs().min(1.),
+        blit::Mode::EvenOdd => 1. - (1. - alpha.abs() % 2.).abs(),
+        blit::Mode::Stroke => alpha.clamp(0., 1.),
+    }
+}
+
+/// The tuning constants in `blit_fxaa.frag`
+const FXAA_REDUCE_MIN: f32 = 1. / 128.;
+const FXAA_REDUCE_MUL: f32 = 1. / 8.;
+const FXAA_SPAN_MAX: f32 = 8.;
+
+/// `blit_fxaa.frag` for the pixel at `(x, y)`, given the coverage of every pixel.
+fn fxaa(coverage: &[f32], (width, height): (u32, u32), (x, y): (u32, u32)) -> f32 {
+    let texel = |x: i32, y: i32| {
+        let x = x.max(0).min(width as i32 - 1);
+        let y = y.max(0).min(height as i32 - 1);
+
+        coverage[(y as u32 * width + x as u32) as usize]
+    };
+    let coverage_at = |position: Vector2<f32>| {
+        let p = position - Vector2::new(0.5, 0.5);
+        let (x, y) = (p.x.floor() as i32, p.y.floor() as i32);
+        let f = p - Vector2::new(p.x.floor(), p.y.floor());
+        let mix = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        mix(
+            mix(texel(x, y), texel(x + 1, y), f.x),
+            mix(texel(x, y + 1), texel(x + 1, y + 1), f.x),
+            f.y,
+        )
+    };
+
+    let position = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+    let (x, y) = (x as i32, y as i32);
+    let (nw, ne, sw, se, m) = (
+        texel(x - 1, y - 1),
+        texel(x + 1, y - 1),
+        texel(x - 1, y + 1),
+        texel(x + 1, y + 1),
+        texel(x, y),
+    );
+
+    let lowest = m.min(nw).min(ne).min(sw).min(se);
+    let highest = m.max(nw).max(ne).max(sw).max(se);
+
+    let direction = Vector2::new(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    let reduce = ((nw + ne + sw + se) * 0.25 * FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+    let scale = 1. / (direction.x.abs().min(direction.y.abs()) + reduce);
+    let span = |c: f32| (c * scale).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+    let direction = Vector2::new(span(direction.x), span(direction.y));
+
+    let near = 0.5
+        * (coverage_at(position + direction * (1. / 3. - 0.5))
+            + coverage_at(position + direction * (2. / 3. - 0.5)));
+    let far = near * 0.5
+        + 0.25
+            * (coverage_at(position + direction * -0.5) + coverage_at(position + direction * 0.5));
+
+    if far < lowest || far > highest {
+        near
+    } else {
+        far
+    }
+}
+
+/// Calls `fragment` with the sample index and the interpolated inputs for every sample inside
+/// the triangle. Samples exactly on an edge are only drawn for top and left edges, so that
+/// samples on an edge shared by two triangles are drawn exactly once. As on the GPU, the inputs
+/// are interpolated at the centre of the pixel even when there are several samples.
+fn rasterize(
+    (width, height): (u32, u32),
+    samples: &[[f32; 2]],
+    triangle: [curve::Vertex; 3],
+    mut fragment: impl FnMut(usize, Fragment),
+) {
+    // Screen coordinates have the y axis pointing up, whereas pixels are stored from the top
+    let to_pixels = |v: &curve::Vertex| {
+        Vector2::new(
+            (v.pos[0] + 1.) / 2. * width as f32,
+            (1. - v.pos[1]) / 2. * height as f32,
+        )
+    };
+    let edge = |a: Vector2<f32>, b: Vector2<f32>, p: Vector2<f32>| {
+        (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+    };
+
+    let mut points = [
+        to_pixels(&triangle[0]),
+        to_pixels(&triangle[1]),
+        to_pixels(&triangle[2]),
+    ];
+    let mut vertices = triangle;
+    let area = edge(points[0], points[1], points[2]);
+
+    // `WINDING_MODE` is counter-clockwise with the y axis pointing up, which is clockwise once
+    // it's flipped to point down.
+    let front_facing = area < 0.;
+
+    // This also skips degenerate triangles and ones with NaN vertices
+    if !area.is_normal() {
+        return;
+    }
+
+    if area < 0. {
+        points.swap(1, 2);
+        vertices.swap(1, 2);
+    }
+
+    let area = area.abs();
+    let is_top_left = |a: Vector2<f32>, b: Vector2<f32>| {
+        let d = b - a;
+
+        d.y < 0. || (d.y == 0. && d.x > 0.)
+    };
+    let edges = [(1, 2), (2, 0), (0, 1)];
+
+    let min = |f: fn(&Vector2<f32>) -> f32| points.iter().map(f).fold(f32::INFINITY, f32::min);
+    let max = |f: fn(&Vector2<f32>) -> f32| points.iter().map(f).fold(f32::NEG_INFINITY, f32::max);
+
+    let (x0, x1) = (
+        min(|p| p.x).max(0.) as u32,
+        (max(|p| p.x).ceil() as u32).min(width),
+    );
+    let (y0, y1) = (
+        min(|p| p.y).max(0.) as u32,
+        (max(|p| p.y).ceil() as u32).min(height),
+    );
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let pixel = Vector2::new(x as f32, y as f32);
+            let is_inside = |p: Vector2<f32>| {
+                edges.iter().all(|&(a, b)| {
+                    let w = edge(points[a], points[b], p);
+
+                    w > 0. || (w == 0. && is_top_left(points[a], points[b]))
+                })
+            };
+            let mut covered = samples
+                .iter()
+                .enumerate()
+                .filter(|(_, &[sx, sy])| is_inside(pixel + Vector2::new(sx, sy)))
+                .map(|(i, _)| i)
+                .peekable();
+
+            if covered.peek().is_none() {
+                continue;
+            }
+
+            let interpolate = |p: Vector2<f32>, f: fn(&curve::Vertex) -> f32| {
+                let weights = [
+                    edge(points[1], points[2], p) / area,
+                    edge(points[2], points[0], p) / area,
+                    edge(points[0], points[1], p) / area,
+                ];
+
+                weights
+                    .iter()
+                    .zip(&vertices)
+                    .map(|(w, v)| w * f(v))
+                    .sum::<f32>()
+            };
+            // Everything is interpolated linearly, so the derivatives are the same everywhere
+            let centre = pixel + Vector2::new(0.5, 0.5);
+            let derivative = |f: fn(&curve::Vertex) -> f32| {
+                Vector2::new(
+                    interpolate(centre + Vector2::unit_x(), f) - interpolate(centre, f),
+                    interpolate(centre + Vector2::unit_y(), f) - interpolate(centre, f),
+                )
+            };
+            let inputs = Fragment {
+                uv: Vector2::new(
+                    interpolate(centre, |v| v.uv[0]),
+                    interpolate(centre, |v| v.uv[1]),
+                ),
+                du: derivative(|v| v.uv[0]),
+                dv: derivative(|v| v.uv[1]),
+                sign: interpolate(centre, |v| v.sign),
+                front_facing,
+            };
+            let first_sample = (y * width + x) as usize * samples.len();
+
+            for i in covered {
+                fragment(first_sample + i, inputs);
+            }
+        }
+    }
+}
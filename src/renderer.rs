@@ -1,30 +1,187 @@
 use crate::{
+    blend::BlendMode,
+    bounds::Bounds,
     cache::{self, Cache, CacheCommon},
-    pipelines::{self, blit, curve},
+    color::ColorSpace,
+    paint::{GradientShape, Image, Paint, RAMP_WIDTH},
+    path::Path,
+    pipelines::{self, blit, curve, fill},
+    scene::{Color, FillRule, Scene},
+    tessellate::{self, InvalidVertex, Spacing},
 };
-use cgmath::{InnerSpace, Vector2};
-use std::num::NonZeroU8;
+use cgmath::{Matrix3, SquareMatrix, Vector2};
+use std::{collections::HashMap, fmt, num::NonZeroU8, sync::Arc};
+use wgpu::util::DeviceExt;
+
+const MAX_VERTICES_BEFORE_FLUSH: usize = u16::MAX as usize;
+
+/// The distance that `Canvas::render` strokes either side of the path. Specified in screen
+/// coordinates. This code assumes a square screen right now but extending it to support
+/// arbitrary aspect ratios would be relatively trivial.
+pub const STROKE_WIDTH: f32 = 0.05;
+
+/// A path whose tessellation has a NaN or infinite vertex, as found by `Options::validate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidTessellation {
+    /// The index of the path in the scene, which is always 0 for `Canvas::render`.
+    pub path: usize,
+    pub vertex: InvalidVertex,
+}
 
-const MAX_VERTICES_BEFORE_FLUSH: usize = std::u16::MAX as usize;
+impl fmt::Display for InvalidTessellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid tessellation of path {}: {}",
+            self.path, self.vertex
+        )
+    }
+}
+
+impl std::error::Error for InvalidTessellation {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.vertex)
+    }
+}
 
 // TODO: add atlas
 pub struct Canvas {
-    blit_verts: wgpu::Buffer,
+    size: (u32, u32),
+    blit_verts: cache::BufferCache<blit::Vertex>,
     vertices: cache::BufferCache<pipelines::curve::Vertex>,
     indices: cache::BufferCache<u16>,
-    curve_pipeline: curve::Pipeline,
-    blit_pipeline: blit::Pipeline,
+    /// The images of the patterns in a frame, premultiplied like the ramps of gradients.
+    images: cache::TextureCache<[f32; 4]>,
+    passes: Passes,
+}
+
+/// The intermediate buffer, the ramp texture and the pipelines that draw into and out of them,
+/// which depend on the number of samples per pixel, whether FXAA is on, the colour space of the
+/// render target, the number of gradients and the image texture.
+struct Passes {
+    sample_count: SampleCount,
+    fxaa: bool,
+    color_space: ColorSpace,
+    /// The curve and fill pipelines for each channel of the intermediate buffer.
+    curve_pipelines: Vec<curve::Pipeline>,
+    fill_pipelines: Vec<fill::Pipeline>,
+    /// A blit pipeline for each blend mode that has been drawn with, as the blend state is part
+    /// of the pipeline. They are only built when they're first needed.
+    blit_pipelines: HashMap<BlendMode, blit::Pipeline>,
+    sampler: wgpu::Sampler,
     intermediate_buffer: wgpu::TextureView,
+    /// The ramps of the gradients in a frame, one per row.
+    ramp_texture: wgpu::Texture,
+    ramp_view: wgpu::TextureView,
+    ramp_rows: u32,
+    image_view: wgpu::TextureView,
+    /// The size of the image texture that the blit pipeline was built with, which is replaced
+    /// whenever it grows.
+    image_size: (u32, u32),
+}
+
+/// A single path to be drawn into the intermediate buffer and then blitted to the screen.
+pub(crate) struct Draw {
+    pub kind: DrawKind,
+    /// In screen coordinates, like the vertices.
+    pub paint: Paint,
+    pub blend: BlendMode,
+    pub vertices: Vec<curve::Vertex>,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum DrawKind {
+    Stroke,
+    Fill(FillRule),
+}
+
+impl DrawKind {
+    /// Whether the two kinds are drawn into the intermediate buffer with the same pipeline,
+    /// which is the case for fills with different rules.
+    fn same_pipeline(self, other: Self) -> bool {
+        matches!(
+            (self, other),
+            (DrawKind::Stroke, DrawKind::Stroke) | (DrawKind::Fill(_), DrawKind::Fill(_))
+        )
+    }
+
+    pub fn blit_mode(self) -> blit::Mode {
+        match self {
+            DrawKind::Stroke => blit::Mode::Stroke,
+            DrawKind::Fill(FillRule::NonZero) => blit::Mode::NonZero,
+            DrawKind::Fill(FillRule::EvenOdd) => blit::Mode::EvenOdd,
+        }
+    }
+}
+
+/// The number of samples per pixel in the intermediate buffer, for multisample anti-aliasing.
+/// Only the edges of triangles are sampled more than once per pixel, as the fragment shaders
+/// still run once per pixel. Strokes and the curved edges of fills are already anti-aliased by
+/// `curve.frag` and `fill.frag` without multisampling, but the straight edges of fills are not,
+/// as they are the hard edges of the fan triangles. Nor are curves that are within half a pixel
+/// of their chord, which includes the ends of every curve.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SampleCount {
+    #[default]
+    One = 1,
+    Four = 4,
+    Eight = 8,
+}
+
+impl SampleCount {
+    pub fn get(self) -> u32 {
+        self as u32
+    }
 }
 
 pub struct Options {
     pub num_segments: NonZeroU8,
+    /// How the `num_segments` pieces of each quadratic are spaced along it.
+    pub spacing: Spacing,
+    pub sample_count: SampleCount,
+    /// Smooth the edges of each path with FXAA as it is blitted to the screen. This is much
+    /// cheaper than multisampling, but blurs fine detail, and is ignored when multisampling.
+    pub fxaa: bool,
+    /// The colour space of the render target, which must have the format from
+    /// `ColorSpace::format`.
+    pub color_space: ColorSpace,
+    /// The straight sRGB colour that the render target is cleared to before anything is drawn.
+    pub clear_color: Color,
+    /// Check every draw for NaN or infinite vertices before it is drawn, and return the
+    /// offending vertex as an error instead of drawing anything if there are any.
+    pub validate: bool,
 }
 
-impl Canvas {
-    pub fn new(device: &wgpu::Device, (width, height): (u32, u32)) -> Self {
-        use wgpu::util::DeviceExt as _;
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            num_segments: NonZeroU8::new(2).unwrap(),
+            spacing: Spacing::default(),
+            sample_count: SampleCount::default(),
+            fxaa: false,
+            color_space: ColorSpace::default(),
+            clear_color: [0., 0., 0., 0.],
+            validate: false,
+        }
+    }
+}
 
+impl Passes {
+    /// Builds the passes for `options`, with room for `ramp_rows` gradients, reading patterns
+    /// from `images`.
+    fn new(
+        device: &wgpu::Device,
+        (width, height): (u32, u32),
+        options: &Options,
+        ramp_rows: u32,
+        images: &cache::TextureCache<[f32; 4]>,
+    ) -> Self {
+        let Options {
+            sample_count,
+            fxaa,
+            color_space,
+            ..
+        } = *options;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("blit_intermediate"),
             size: wgpu::Extent3d {
@@ -33,7 +190,7 @@ impl Canvas {
                 depth: 1,
             },
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count: sample_count.get(),
             dimension: wgpu::TextureDimension::D2,
             format: blit::INTERMEDIATE_TEXTURE_FORMAT,
             usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
@@ -53,309 +210,558 @@ impl Canvas {
         });
 
         let intermediate_buffer = texture.create_view(&Default::default());
+        let ramp_rows = ramp_rows.max(1);
+        let ramp_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("blit_ramps"),
+            size: wgpu::Extent3d {
+                width: RAMP_WIDTH as u32,
+                height: ramp_rows,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: blit::RAMP_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
 
+        let curve_pipelines = (0..blit::INTERMEDIATE_CHANNELS)
+            .map(|channel| curve::build(device, sample_count.get(), channel))
+            .collect();
+        let fill_pipelines = (0..blit::INTERMEDIATE_CHANNELS)
+            .map(|channel| fill::build(device, sample_count.get(), channel))
+            .collect();
+
+        Self {
+            sample_count,
+            fxaa,
+            color_space,
+            curve_pipelines,
+            fill_pipelines,
+            blit_pipelines: HashMap::new(),
+            sampler,
+            intermediate_buffer,
+            ramp_view: ramp_texture.create_view(&Default::default()),
+            ramp_texture,
+            ramp_rows,
+            image_view: images.create_view(&Default::default()),
+            image_size: images.size(),
+        }
+    }
+
+    /// The pipeline that draws `kind` into `channel` of the intermediate buffer.
+    fn intermediate_pipeline(&self, kind: DrawKind, channel: u32) -> &wgpu::RenderPipeline {
+        let pipelines = match kind {
+            DrawKind::Stroke => &self.curve_pipelines,
+            DrawKind::Fill(_) => &self.fill_pipelines,
+        };
+
+        &pipelines[channel as usize].pipeline
+    }
+
+    /// Builds the blit pipeline for `blend` if it hasn't been already.
+    fn prepare_blit(&mut self, device: &wgpu::Device, blend: BlendMode) {
+        if self.blit_pipelines.contains_key(&blend) {
+            return;
+        }
+
+        let pipeline = blit::build(
+            device,
+            &self.sampler,
+            &self.intermediate_buffer,
+            &blit::PaintTextures {
+                ramps: &self.ramp_view,
+                images: &self.image_view,
+            },
+            self.sample_count.get(),
+            self.fxaa,
+            blit::Target {
+                format: self.color_space.format(),
+                blend,
+            },
+        );
+
+        self.blit_pipelines.insert(blend, pipeline);
+    }
+}
+
+impl Canvas {
+    pub fn new(device: &wgpu::Device, size: (u32, u32)) -> Self {
         let vertices = cache::BufferCache::new(wgpu::BufferUsage::VERTEX);
         let indices = cache::BufferCache::new(wgpu::BufferUsage::INDEX);
+        let blit_verts = cache::BufferCache::new(wgpu::BufferUsage::VERTEX);
+        let images = cache::TextureCache::new(device, blit::IMAGE_TEXTURE_FORMAT);
+        let passes = Passes::new(device, size, &Options::default(), 1, &images);
 
-        let curve_pipeline = curve::build(device);
-        let blit_pipeline = blit::build(device, &sampler, &intermediate_buffer);
-
-        let blit_verts = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&[
-                blit::Vertex {
-                    pos: [-1., -1.],
-                    uv: [0., 1.],
-                },
-                blit::Vertex {
-                    pos: [1., -1.],
-                    uv: [1., 1.],
-                },
-                blit::Vertex {
-                    pos: [1., 1.],
-                    uv: [1., 0.],
-                },
-                blit::Vertex {
-                    pos: [1., 1.],
-                    uv: [1., 0.],
-                },
-                blit::Vertex {
-                    pos: [-1., 1.],
-                    uv: [0., 0.],
-                },
-                blit::Vertex {
-                    pos: [-1., -1.],
-                    uv: [0., 1.],
-                },
-            ]),
-            usage: wgpu::BufferUsage::VERTEX,
-        });
         Self {
+            size,
             vertices,
             indices,
-            curve_pipeline,
-            blit_pipeline,
-            intermediate_buffer,
             blit_verts,
+            images,
+            passes,
         }
     }
 
-    pub fn render<V>(
+    pub fn render(
         &mut self,
         device: &wgpu::Device,
         screen_tex: &wgpu::TextureView,
         // TODO: Make this "paths" plural and add some kind of separation so we can independently
         //       cache the paths.
-        path: impl IntoIterator<Item = V>,
+        path: &Path,
         options: &Options,
-    ) -> Option<wgpu::CommandBuffer>
-    where
-        V: Into<Vector2<f32>>,
-    {
-        use itertools::Itertools;
+    ) -> Result<Option<wgpu::CommandBuffer>, InvalidTessellation> {
+        let draws = path_draws(path, options, self.size)?;
+
+        Ok(self.draw(device, screen_tex, draws, options))
+    }
+
+    /// Draws every path in `scene` in order, with the scene's view box scaled to fit the screen.
+    /// Paths that are entirely off-screen are skipped.
+    pub fn render_scene(
+        &mut self,
+        device: &wgpu::Device,
+        screen_tex: &wgpu::TextureView,
+        scene: &Scene,
+        options: &Options,
+    ) -> Result<Option<wgpu::CommandBuffer>, InvalidTessellation> {
+        let draws = scene_draws(scene, options, self.size)?;
+
+        Ok(self.draw(device, screen_tex, draws, options))
+    }
+
+    /// Each draw is rendered into a channel of the intermediate buffer of its own, as the
+    /// coverage of everything in a channel adds up, and then blended onto the screen with its
+    /// blend mode. Up to `blit::INTERMEDIATE_CHANNELS` consecutive draws with the same blend mode
+    /// and intermediate pipeline are batched into one intermediate pass and one blit pass, which
+    /// blits them in order. Draws are never reordered, as blending isn't commutative, but all
+    /// the draws with the same blend mode share a blit pipeline. The intermediate buffer and
+    /// pipelines are rebuilt if any of the options that they depend on have changed, if there
+    /// are more gradients than the ramp texture has room for or if the image texture had to
+    /// grow.
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        screen_tex: &wgpu::TextureView,
+        draws: Vec<Draw>,
+        options: &Options,
+    ) -> Option<wgpu::CommandBuffer> {
+        let draws = draws
+            .into_iter()
+            .filter(|draw| !draw.vertices.is_empty())
+            .collect::<Vec<_>>();
+        let ramps = draws
+            .iter()
+            .filter_map(|draw| draw.paint.gradient())
+            .flat_map(|gradient| gradient.ramp(options.color_space))
+            .collect::<Vec<_>>();
+        let ramp_rows = (ramps.len() / RAMP_WIDTH) as u32;
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("render"),
         });
 
-        // Specified in screen coordinates. This code assumes a square screen right now but
-        // extending it to support arbitrary aspect ratios would be relatively trivial.
-        let width: f32 = 0.05;
+        // Each image is only uploaded once, however many patterns use it
+        let mut image_rects = HashMap::new();
+
+        self.images.clear();
+
+        for pattern in draws.iter().filter_map(|draw| draw.paint.pattern()) {
+            let images = &mut self.images;
+            let image = &pattern.image;
+
+            image_rects.entry(Arc::as_ptr(image)).or_insert_with(|| {
+                images.append((
+                    (image.width, image.height),
+                    image.texels(options.color_space),
+                ))
+            });
+        }
+
+        self.images.update(device, &mut encoder);
+
+        let is_stale = self.passes.sample_count != options.sample_count
+            || self.passes.fxaa != options.fxaa
+            || self.passes.color_space != options.color_space
+            || self.passes.ramp_rows < ramp_rows
+            || self.passes.image_size != self.images.size();
+
+        if is_stale {
+            self.passes = Passes::new(
+                device,
+                self.size,
+                options,
+                ramp_rows.next_power_of_two(),
+                &self.images,
+            );
+        }
+
+        let clear_color = options.color_space.clear_color(options.clear_color);
+
+        // Triangles must not be split between chunks
+        const CHUNK_SIZE: usize = MAX_VERTICES_BEFORE_FLUSH - MAX_VERTICES_BEFORE_FLUSH % 3;
 
         self.vertices.clear();
         self.indices.clear();
+        self.blit_verts.clear();
 
-        let num_segments = options.num_segments.get() as u32;
-        let num_points = (num_segments * 2) - 1;
-        let dt = ((num_points + 1) as f32).recip();
-
-        let verts = self.vertices.append(
-            path.into_iter()
-                .map(Into::into)
-                .tuple_windows()
-                .step_by(2)
-                .flat_map(|(start, control, end)| {
-                    /// Calculates a quadratic bezier that passes through the start and end
-                    /// point at t=0 and t=1 respectively, and passes through the midpoint
-                    /// at t=midpoint_t. It doesn't take gradient into account, which leads
-                    /// to discontinuities and should be addressed.
-                    fn calc_quadratic(
-                        start: Vector2<f32>,
-                        mid: Vector2<f32>,
-                        end: Vector2<f32>,
-                        midpoint_t: f32,
-                    ) -> (Vector2<f32>, Vector2<f32>, Vector2<f32>) {
-                        let t = midpoint_t;
-
-                        (
-                            start,
-                            (mid - t.powi(2) * end - (1. - t).powi(2) * start)
-                                / (2. * t * (1. - t)),
-                            end,
-                        )
-                    }
+        if ramp_rows > 0 {
+            const BYTES_PER_TEXEL: u32 = 16;
 
-                    fn point_on_quadratic(
-                        start: Vector2<f32>,
-                        control: Vector2<f32>,
-                        end: Vector2<f32>,
-                        t: f32,
-                    ) -> Vector2<f32> {
-                        start * (1. - t).powi(2) + 2. * control * t * (1. - t) + end * t.powi(2)
-                    }
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("blit_ramps"),
+                contents: bytemuck::cast_slice(&ramps),
+                usage: wgpu::BufferUsage::COPY_SRC,
+            });
 
-                    fn normal_at(
-                        start: Vector2<f32>,
-                        control: Vector2<f32>,
-                        end: Vector2<f32>,
-                        t: f32,
-                    ) -> Vector2<f32> {
-                        // Basic derivation of bezier formula
-                        let tangent = (2. * (1. - t) * (control - start)
-                            + 2. * t * (end - control))
-                            .normalize();
-
-                        // Rotate 90 degrees
-                        Vector2::from((-tangent.y, tangent.x))
-                    }
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: &buffer,
+                    layout: wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: RAMP_WIDTH as u32 * BYTES_PER_TEXEL,
+                        rows_per_image: ramp_rows,
+                    },
+                },
+                wgpu::TextureCopyView {
+                    texture: &self.passes.ramp_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::Extent3d {
+                    width: RAMP_WIDTH as u32,
+                    height: ramp_rows,
+                    depth: 1,
+                },
+            );
+        }
 
-                    /// Triangulate a quad. This function is extremely bad and
-                    /// should handle automatically generating correct vertices
-                    /// for both clockwise and counter-clockwise quads.
-                    fn quad(input: [Vector2<f32>; 4]) -> [Vector2<f32>; 6] {
-                        [input[0], input[2], input[3], input[3], input[1], input[0]]
+        let mut next_ramp = 0;
+        let mut previous: Option<(DrawKind, BlendMode, u32)> = None;
+        let draws = draws
+            .into_iter()
+            .map(|draw| {
+                // A draw starts a new batch from the first channel unless it can join the last one
+                let channel = match previous {
+                    Some((kind, blend, channel))
+                        if kind.same_pipeline(draw.kind)
+                            && blend == draw.blend
+                            && channel + 1 < blit::INTERMEDIATE_CHANNELS =>
+                    {
+                        channel + 1
                     }
-
-                    // TODO: This only works for counterclockwise curves, but it shouldn't be too
-                    //       difficult to make it work for clockwise curves. This would probably
-                    //       make it look better for extreme angles, too, as these issues seem
-                    //       to be connected.
-                    (0..num_points)
-                        // We step by 2 but triangulate 3 points from each step, as the end of 1 curve
-                        // is the start of the next but the control points are not shared. The points
-                        // look like:
-                        //
-                        // [start1, control1, end1/start2, control2, end2/start3, ..]
-                        .step_by(2)
-                        .flat_map(|i| {
-                            let i = i as f32 * dt;
-
-                            let (a, b, c) = (i, i + dt, i + dt * 2.);
-
-                            let (a_point, b_point, c_point) = (
-                                point_on_quadratic(start, control, end, a),
-                                point_on_quadratic(start, control, end, b),
-                                point_on_quadratic(start, control, end, c),
-                            );
-                            let (a_norm, b_norm, c_norm) = (
-                                normal_at(start, control, end, a),
-                                normal_at(start, control, end, b),
-                                normal_at(start, control, end, c),
-                            );
-
-                            // TODO: This leads to weird C1 discontinuities, so we might want to solve for
-                            //       gradient at the start and end and minimise positional error, or have
-                            //       some other way to normalise the gradient between segments to maintain
-                            //       C1 continuity.
-                            let perc = (b - a) / (c - a);
-                            let inner = calc_quadratic(
-                                a_point + a_norm * width,
-                                b_point + b_norm * width,
-                                c_point + c_norm * width,
-                                perc,
-                            );
-
-                            let outer = calc_quadratic(
-                                a_point - a_norm * width,
-                                b_point - b_norm * width,
-                                c_point - c_norm * width,
-                                perc,
-                            );
-
-                            let mut out = vec![
-                                curve::Vertex {
-                                    pos: outer.0.into(),
-                                    uv: [0., 0.],
-                                    sign: 1.,
-                                },
-                                curve::Vertex {
-                                    pos: outer.1.into(),
-                                    uv: [0.5, 0.],
-                                    sign: 1.,
-                                },
-                                curve::Vertex {
-                                    pos: outer.2.into(),
-                                    uv: [1., 1.],
-                                    sign: 1.,
-                                },
-                                curve::Vertex {
-                                    pos: inner.0.into(),
-                                    uv: [0., 0.],
-                                    sign: -1.,
-                                },
-                                curve::Vertex {
-                                    pos: inner.1.into(),
-                                    uv: [0.5, 0.],
-                                    sign: -1.,
-                                },
-                                curve::Vertex {
-                                    pos: inner.2.into(),
-                                    uv: [1., 1.],
-                                    sign: -1.,
-                                },
-                            ];
-
-                            out.extend(
-                                quad([
-                                    inner.0,
-                                    inner.1,
-                                    outer.0,
-                                    point_on_quadratic(outer.0, outer.1, outer.2, 0.5),
-                                ])
-                                .iter()
-                                .copied()
-                                .map(|p| curve::Vertex {
-                                    pos: p.into(),
-                                    uv: Default::default(),
-                                    sign: 0.,
-                                }),
-                            );
-
-                            out.extend(
-                                quad([
-                                    inner.1,
-                                    inner.2,
-                                    point_on_quadratic(outer.0, outer.1, outer.2, 0.5),
-                                    outer.2,
-                                ])
-                                .iter()
-                                .copied()
-                                .map(|p| curve::Vertex {
-                                    pos: p.into(),
-                                    uv: Default::default(),
-                                    sign: 0.,
-                                }),
-                            );
-
-                            out
-                        })
-                        .collect::<Vec<_>>()
-                }),
-        );
-
-        self.indices.append(verts.start as u16..verts.end as u16);
+                    _ => 0,
+                };
+
+                previous = Some((draw.kind, draw.blend, channel));
+
+                let blit_vertex = blit_vertex(
+                    &draw.paint,
+                    draw.kind.blit_mode(),
+                    &mut next_ramp,
+                    &image_rects,
+                    options.color_space,
+                );
+                let vertices = self.vertices.append(draw.vertices);
+                let blit_verts = self.blit_verts.append(
+                    [
+                        ([-1., -1.], [0., 1.]),
+                        ([1., -1.], [1., 1.]),
+                        ([1., 1.], [1., 0.]),
+                        ([1., 1.], [1., 0.]),
+                        ([-1., 1.], [0., 0.]),
+                        ([-1., -1.], [0., 1.]),
+                    ]
+                    .iter()
+                    .map(|&(pos, uv)| blit::Vertex {
+                        pos,
+                        uv,
+                        channel,
+                        ..blit_vertex
+                    }),
+                );
+
+                (draw.kind, draw.blend, channel, vertices, blit_verts)
+            })
+            .collect::<Vec<_>>();
+
+        // The indices are the same for every draw, we just change the base vertex.
+        let max_len = draws
+            .iter()
+            .map(|(_, _, _, vertices, _)| (vertices.end - vertices.start) as usize)
+            .max()
+            .unwrap_or_default()
+            .min(CHUNK_SIZE);
+        self.indices.append(0..max_len as u16);
 
         self.vertices.update(device, &mut encoder);
         self.indices.update(device, &mut encoder);
+        self.blit_verts.update(device, &mut encoder);
 
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &self.intermediate_buffer,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.,
-                            g: 0.,
-                            b: 0.,
-                            a: 0.,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            rpass.set_pipeline(&self.curve_pipeline.pipeline);
-            rpass.set_vertex_buffer(0, self.vertices.as_ref()?.slice(..));
-            rpass.set_index_buffer(self.indices.as_ref()?.slice(..));
-            rpass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);
+        for &(_, blend, _, _, _) in &draws {
+            self.passes.prepare_blit(device, blend);
         }
 
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        if draws.is_empty() {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
                     attachment: screen_tex,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.,
-                            g: 0.,
-                            b: 0.,
-                            a: 0.,
-                        }),
+                        load: wgpu::LoadOp::Clear(clear_color),
                         store: true,
                     },
                 }],
                 depth_stencil_attachment: None,
             });
+        }
 
-            rpass.set_pipeline(&self.blit_pipeline.pipeline);
-            rpass.set_bind_group(0, &self.blit_pipeline.bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.blit_verts.slice(..));
-            rpass.draw(0..6, 0..1)
+        // Each batch starts with a draw in the first channel
+        let batches = draws.chunk_by(|_, &(_, _, channel, _, _)| channel != 0);
+
+        for (i, batch) in batches.enumerate() {
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: &self.passes.intermediate_buffer,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color {
+                                r: 0.,
+                                g: 0.,
+                                b: 0.,
+                                a: 0.,
+                            }),
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+
+                rpass.set_vertex_buffer(0, self.vertices.as_ref()?.slice(..));
+                rpass.set_index_buffer(self.indices.as_ref()?.slice(..));
+
+                for (kind, _, channel, vertices, _) in batch {
+                    rpass.set_pipeline(self.passes.intermediate_pipeline(*kind, *channel));
+
+                    for start in (vertices.start..vertices.end).step_by(CHUNK_SIZE) {
+                        let len = (vertices.end - start).min(CHUNK_SIZE as u64);
+
+                        rpass.draw_indexed(0..len as u32, start as i32, 0..1);
+                    }
+                }
+            }
+
+            {
+                let (_, blend, _, _, first) = &batch[0];
+                let (_, _, _, _, last) = &batch[batch.len() - 1];
+                let blit_pipeline = &self.passes.blit_pipelines[blend];
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: screen_tex,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: if i == 0 {
+                                wgpu::LoadOp::Clear(clear_color)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+
+                rpass.set_pipeline(&blit_pipeline.pipeline);
+                rpass.set_bind_group(0, &blit_pipeline.bind_group, &[]);
+                rpass.set_vertex_buffer(0, self.blit_verts.as_ref()?.slice(..));
+                rpass.draw(first.start as u32..last.end as u32, 0..1)
+            }
         }
 
         Some(encoder.finish())
     }
 }
+
+/// The blit vertex for a draw with the given paint, without its position. Gradients take the
+/// next row of the ramp texture, in the order that `Canvas::draw` filled it in, and patterns
+/// take the region of the image texture that their image was packed into.
+fn blit_vertex(
+    paint: &Paint,
+    mode: blit::Mode,
+    next_ramp: &mut u32,
+    image_rects: &HashMap<*const Image, cache::Rect>,
+    color_space: ColorSpace,
+) -> blit::Vertex {
+    let solid = blit::Vertex {
+        pos: [0., 0.],
+        uv: [0., 0.],
+        color: [0.; 4],
+        mode: mode as u32,
+        channel: 0,
+        paint: blit::PaintKind::Solid as u32,
+        spread: 0,
+        gradient: [0.; 4],
+        ramp: 0,
+        pattern_x: [0.; 3],
+        pattern_y: [0.; 3],
+        image: [0; 4],
+        filter: 0,
+    };
+
+    if let Some(gradient) = paint.gradient() {
+        let (kind, points) = match gradient.shape {
+            GradientShape::Linear { start, end } => {
+                (blit::PaintKind::Linear, [start.x, start.y, end.x, end.y])
+            }
+            GradientShape::Radial { centre, radius } => {
+                (blit::PaintKind::Radial, [centre.x, centre.y, radius, 0.])
+            }
+        };
+        let ramp = *next_ramp;
+
+        *next_ramp += 1;
+
+        blit::Vertex {
+            paint: kind as u32,
+            spread: gradient.spread as u32,
+            gradient: points,
+            ramp,
+            ..solid
+        }
+    } else if let Some(pattern) = paint.pattern() {
+        // Patterns are never degenerate here, so the transform can be inverted
+        let inverse = pattern.transform.invert().unwrap_or_else(Matrix3::identity);
+
+        blit::Vertex {
+            paint: blit::PaintKind::Pattern as u32,
+            spread: pattern.spread as u32,
+            pattern_x: [inverse.x.x, inverse.y.x, inverse.z.x],
+            pattern_y: [inverse.x.y, inverse.y.y, inverse.z.y],
+            image: image_rects[&Arc::as_ptr(&pattern.image)],
+            filter: pattern.filter as u32,
+            ..solid
+        }
+    } else {
+        blit::Vertex {
+            color: color_space.premultiply(paint.as_solid().unwrap_or([0.; 4])),
+            ..solid
+        }
+    }
+}
+
+/// The size of a pixel in screen coordinates, for a target of the given size. Pixels are taken
+/// to be square, using the shorter side.
+fn pixel_size((width, height): (u32, u32)) -> f32 {
+    2. / width.min(height).max(1) as f32
+}
+
+/// The draws for `Canvas::render`, which strokes a single path in white, for a target of the
+/// given size.
+pub(crate) fn path_draws(
+    path: &Path,
+    options: &Options,
+    size: (u32, u32),
+) -> Result<Vec<Draw>, InvalidTessellation> {
+    let vertices = tessellate::stroke(
+        path,
+        STROKE_WIDTH,
+        options.num_segments,
+        options.spacing,
+        pixel_size(size),
+    );
+
+    validated(
+        Some((
+            0,
+            Draw {
+                kind: DrawKind::Stroke,
+                paint: Paint::Solid([1., 1., 1., 1.]),
+                blend: BlendMode::Normal,
+                vertices,
+            },
+        )),
+        options,
+    )
+}
+
+/// The draws for `Canvas::render_scene`, for a target of the given size.
+pub(crate) fn scene_draws(
+    scene: &Scene,
+    options: &Options,
+    size: (u32, u32),
+) -> Result<Vec<Draw>, InvalidTessellation> {
+    let [x, y, width, height] = scene.view_box;
+    let scale = 2. / width.max(height);
+    let centre = Vector2::new(x + width / 2., y + height / 2.);
+
+    // The scene's y axis points down, whereas in screen coordinates it points up
+    let to_screen = |p: Vector2<f32>| {
+        let p = (p - centre) * scale;
+
+        Vector2::new(p.x, -p.y)
+    };
+
+    // Paths outside the square that is mapped onto the screen are never tessellated, unless
+    // their blend mode changes the target outside them
+    let visible = Bounds::from_point(centre).expanded(width.max(height) / 2.);
+    let paths = scene.paths();
+    let mut drawn = scene.index().query(visible);
+
+    drawn.extend((0..paths.len()).filter(|&i| !paths[i].blend.is_bounded()));
+    drawn.sort_unstable();
+    drawn.dedup();
+
+    let draws = drawn.into_iter().flat_map(|i| {
+        let scene_path = &paths[i];
+        let path = scene_path.path.map_points(to_screen);
+
+        let fill = scene_path.fill.as_ref().map(|fill| Draw {
+            kind: DrawKind::Fill(fill.rule),
+            paint: fill.paint.map_points(to_screen),
+            blend: scene_path.blend,
+            vertices: tessellate::fill(&path),
+        });
+        let stroke = scene_path.stroke.as_ref().map(|stroke| {
+            let styled = stroke
+                .styled_path(&scene_path.path)
+                .map(|path| path.map_points(to_screen));
+
+            Draw {
+                kind: DrawKind::Stroke,
+                paint: stroke.paint.map_points(to_screen),
+                blend: scene_path.blend,
+                vertices: tessellate::stroke(
+                    styled.as_ref().unwrap_or(&path),
+                    stroke.width * scale / 2.,
+                    options.num_segments,
+                    options.spacing,
+                    pixel_size(size),
+                ),
+            }
+        });
+
+        fill.into_iter().chain(stroke).map(move |draw| (i, draw))
+    });
+
+    validated(draws, options)
+}
+
+/// Implements `Options::validate`, for draws paired with the index of the path that they draw.
+fn validated(
+    draws: impl IntoIterator<Item = (usize, Draw)>,
+    options: &Options,
+) -> Result<Vec<Draw>, InvalidTessellation> {
+    draws
+        .into_iter()
+        .map(|(path, draw)| {
+            if options.validate {
+                tessellate::validate(&draw.vertices)
+                    .map_err(|vertex| InvalidTessellation { path, vertex })?;
+            }
+
+            Ok(draw)
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+//! # Scenes
+//!
+//! A scene is a list of styled paths, drawn back-to-front by `Canvas::render_scene`. Paths are
+//! stored in user space along with a view box, and are only mapped into screen coordinates when
+//! rendered.
+
+use crate::{
+    blend::BlendMode, bounds::Bounds, dash, paint::Paint, path::Path, spatial::SpatialIndex, trim,
+};
+use std::sync::OnceLock;
+
+/// A straight (non-premultiplied) RGBA colour.
+pub type Color = [f32; 4];
+
+/// How the inside of a self-intersecting path is decided, as in SVG's `fill-rule`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    /// Whether a point that `winding` goes around is inside.
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub paint: Paint,
+    pub rule: FillRule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub paint: Paint,
+    /// The full width of the stroke in user space.
+    pub width: f32,
+    /// The lengths of the dashes and the gaps between them in user space, as in SVG's
+    /// `stroke-dasharray`. The stroke is solid if this is empty.
+    pub dash_array: Vec<f32>,
+    /// How far into the dash pattern the stroke starts, in user space.
+    pub dash_offset: f32,
+    /// The fraction of the length of the path where the stroke starts, from 0 to 1. The dash
+    /// pattern starts here too.
+    pub trim_start: f32,
+    /// The fraction of the length of the path where the stroke ends, from 0 to 1.
+    pub trim_end: f32,
+}
+
+impl Stroke {
+    /// The path that is stroked when `path` is drawn with this style, after trimming and
+    /// dashing, or `None` if it is stroked as it is. Both are measured in user space, as the dash
+    /// pattern is.
+    pub fn styled_path(&self, path: &Path) -> Option<Path> {
+        let mut styled = None;
+
+        if self.trim_start > 0. || self.trim_end < 1. {
+            styled = Some(trim::trim(path, self.trim_start, self.trim_end));
+        }
+
+        if !self.dash_array.is_empty() {
+            styled = Some(dash::dash(
+                styled.as_ref().unwrap_or(path),
+                &self.dash_array,
+                self.dash_offset,
+            ));
+        }
+
+        styled
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenePath {
+    pub path: Path,
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+    /// How both the fill and the stroke are blended onto what is already drawn.
+    pub blend: BlendMode,
+}
+
+impl ScenePath {
+    /// The smallest box containing everything that is drawn for this path, or `None` if nothing
+    /// is. The stroke is taken to cover the whole path, even if it is trimmed or dashed, so that
+    /// the bounds can be found without styling it.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let bounds = self.path.bounds()?;
+        let fill = self.fill.as_ref().map(|_| bounds);
+        let stroke = self
+            .stroke
+            .as_ref()
+            .map(|stroke| bounds.expanded(stroke.width / 2.));
+
+        match (fill, stroke) {
+            (Some(fill), Some(stroke)) => Some(fill.union(stroke)),
+            (fill, stroke) => fill.or(stroke),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scene {
+    /// The region of user space that is mapped onto the canvas, as `[x, y, width, height]`. The
+    /// y axis points down, as in SVG. The view box is scaled uniformly and centred.
+    pub view_box: [f32; 4],
+    paths: Vec<ScenePath>,
+    /// The index of `paths`, which is built the first time that it's needed after they change.
+    index: OnceLock<SpatialIndex>,
+}
+
+impl Scene {
+    pub fn new(view_box: [f32; 4], paths: Vec<ScenePath>) -> Self {
+        Self {
+            view_box,
+            paths,
+            index: OnceLock::new(),
+        }
+    }
+
+    pub fn paths(&self) -> &[ScenePath] {
+        &self.paths
+    }
+
+    /// The paths, for changing them. The index is rebuilt the next time that it's used.
+    pub fn paths_mut(&mut self) -> &mut Vec<ScenePath> {
+        self.index = OnceLock::new();
+
+        &mut self.paths
+    }
+
+    /// Adds a path on top of the others.
+    pub fn push(&mut self, path: ScenePath) {
+        self.paths_mut().push(path);
+    }
+
+    /// An index of the bounds of every path that draws anything, by its position in `paths`.
+    /// It's kept until the paths are changed, so this is cheap to call every frame.
+    pub fn index(&self) -> &SpatialIndex {
+        self.index.get_or_init(|| {
+            SpatialIndex::new(
+                self.paths
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, path)| Some((i, path.bounds()?))),
+            )
+        })
+    }
+}
+
+impl PartialEq for Scene {
+    fn eq(&self, other: &Self) -> bool {
+        self.view_box == other.view_box && self.paths == other.paths
+    }
+}
@@ -0,0 +1,208 @@
+//! # Spatial index
+//!
+//! Finds which of many boxes overlap a region without checking every one, for culling the paths
+//! of a scene to the viewport and for hit testing. The index is a bounding volume hierarchy:
+//! a binary tree where each node holds the box around everything below it, built by splitting
+//! the boxes in half along the longest axis of their centres.
+
+use crate::bounds::Bounds;
+use cgmath::Vector2;
+
+/// The most boxes kept in one leaf, as checking a few boxes directly is cheaper than another
+/// level of the tree.
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(Vec<(usize, Bounds)>),
+    /// The indices of the two children in `SpatialIndex::nodes`.
+    Branch(usize, usize),
+}
+
+/// An index over boxes, each with an ID, which is usually the position of the thing it bounds in
+/// a list.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    /// The box around each node along with the node. The root is the last node.
+    nodes: Vec<(Bounds, Node)>,
+}
+
+impl SpatialIndex {
+    pub fn new(items: impl IntoIterator<Item = (usize, Bounds)>) -> Self {
+        let mut index = Self::default();
+        let items = items.into_iter().collect::<Vec<_>>();
+
+        if !items.is_empty() {
+            index.build(items);
+        }
+
+        index
+    }
+
+    /// Adds the node for `items`, which must not be empty, after the nodes for its children.
+    /// Returns its index.
+    fn build(&mut self, mut items: Vec<(usize, Bounds)>) -> usize {
+        let bounds = items
+            .iter()
+            .skip(1)
+            .fold(items[0].1, |bounds, &(_, item)| bounds.union(item));
+
+        let node = if items.len() <= LEAF_SIZE {
+            Node::Leaf(items)
+        } else {
+            let centres = Bounds::from_points(items.iter().map(|(_, item)| item.centre())).unwrap();
+            let size = centres.size();
+            let key = |bounds: &Bounds| {
+                if size.x >= size.y {
+                    bounds.centre().x
+                } else {
+                    bounds.centre().y
+                }
+            };
+
+            // Boxes that are infinite both ways have no centre, and are sorted after the rest
+            items.sort_by(|(_, a), (_, b)| key(a).total_cmp(&key(b)));
+
+            let right = items.split_off(items.len() / 2);
+
+            Node::Branch(self.build(items), self.build(right))
+        };
+
+        self.nodes.push((bounds, node));
+        self.nodes.len() - 1
+    }
+
+    /// The IDs of every box that overlaps `region`, in ascending order.
+    pub fn query(&self, region: Bounds) -> Vec<usize> {
+        let mut out = Vec::new();
+
+        if !self.nodes.is_empty() {
+            self.collect(self.nodes.len() - 1, &region, &mut out);
+        }
+
+        out.sort_unstable();
+        out
+    }
+
+    /// The IDs of every box that contains `point`, in ascending order.
+    pub fn query_point(&self, point: Vector2<f32>) -> Vec<usize> {
+        self.query(Bounds::from_point(point))
+    }
+
+    fn collect(&self, node: usize, region: &Bounds, out: &mut Vec<usize>) {
+        let (bounds, node) = &self.nodes[node];
+
+        if !bounds.intersects(region) {
+            return;
+        }
+
+        match node {
+            Node::Leaf(items) => out.extend(
+                items
+                    .iter()
+                    .filter(|(_, item)| item.intersects(region))
+                    .map(|&(id, _)| id),
+            ),
+            Node::Branch(left, right) => {
+                self.collect(*left, region, out);
+                self.collect(*right, region, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of unit boxes with gaps between them, numbered in a scrambled order so that the
+    /// IDs don't follow the order of the tree.
+    fn grid() -> Vec<(usize, Bounds)> {
+        (0..10)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let id = (x * 10 + y) * 37 % 100;
+
+                (
+                    id,
+                    Bounds::from_rect([x as f32 * 2., y as f32 * 2., 1., 1.]),
+                )
+            })
+            .collect()
+    }
+
+    fn brute_force(items: &[(usize, Bounds)], region: Bounds) -> Vec<usize> {
+        let mut out = items
+            .iter()
+            .filter(|(_, bounds)| bounds.intersects(&region))
+            .map(|&(id, _)| id)
+            .collect::<Vec<_>>();
+        out.sort_unstable();
+
+        out
+    }
+
+    #[test]
+    fn query_in_ascending_order() {
+        let items = grid();
+        let index = SpatialIndex::new(items.clone());
+
+        for &region in &[
+            [0., 0., 20., 20.],
+            [3.5, 2.5, 5., 9.],
+            [-5., -5., 5.5, 5.5],
+            [1.2, 1.2, 0.5, 0.5],
+            [30., 30., 1., 1.],
+        ] {
+            let region = Bounds::from_rect(region);
+            let found = index.query(region);
+
+            assert!(found.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(found, brute_force(&items, region));
+        }
+
+        assert_eq!(
+            index.query(Bounds::from_rect([0., 0., 20., 20.])).len(),
+            100
+        );
+    }
+
+    #[test]
+    fn query_point() {
+        let index = SpatialIndex::new(vec![
+            (2, Bounds::from_rect([0., 0., 4., 4.])),
+            (0, Bounds::from_rect([1., 1., 1., 1.])),
+            (1, Bounds::from_rect([3., 3., 2., 2.])),
+        ]);
+
+        assert_eq!(index.query_point(Vector2::new(1.5, 1.5)), vec![0, 2]);
+        // Edges are included
+        assert_eq!(index.query_point(Vector2::new(3., 3.)), vec![1, 2]);
+        assert_eq!(index.query_point(Vector2::new(5., 5.)), vec![1]);
+        assert_eq!(index.query_point(Vector2::new(6., 6.)), Vec::<usize>::new());
+        assert!(SpatialIndex::default()
+            .query_point(Vector2::new(0., 0.))
+            .is_empty());
+    }
+
+    #[test]
+    fn infinite_bounds() {
+        // Such as the bounds of a stroke whose width overflowed
+        let infinite = Bounds {
+            min: Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            max: Vector2::new(f32::INFINITY, f32::INFINITY),
+        };
+        let mut items = (0..6).map(|id| (id, infinite)).collect::<Vec<_>>();
+        items.push((6, Bounds::from_rect([0., 0., 1., 1.])));
+        let index = SpatialIndex::new(items);
+
+        assert_eq!(
+            index.query_point(Vector2::new(0.5, 0.5)),
+            (0..7).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            index.query_point(Vector2::new(-1e30, 1e30)),
+            (0..6).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,973 @@
+//! # SVG loading
+//!
+//! Loads an SVG document into a `Scene`. Only the static subset of SVG that maps onto what the
+//! renderer can draw is supported: `<path>`, the basic shapes and `<g>` with `transform`, plus
+//! solid-colour `fill` and (optionally dashed) `stroke`. Everything else (gradients, text,
+//! `<use>`, stylesheets) is skipped rather than treated as an error, so that most icons still
+//! load, and a style declaration that can't be parsed is dropped, as it would be by a browser.
+//!
+//! Transforms are applied to the points when loading, which is exact as beziers are invariant
+//! under affine transforms. Stroke widths and dashes are scaled by the average scale of the
+//! transform, so non-uniform scales are only approximated. Group opacity is approximated by
+//! multiplying it into the colour of each child, and `mix-blend-mode` on a group by blending each
+//! child with it.
+
+use crate::{
+    blend::BlendMode,
+    path::{self, PathBuilder},
+    scene::{Color, Fill, FillRule, Scene, ScenePath, Stroke},
+};
+use cgmath::{Matrix3, SquareMatrix, Vector2};
+use std::{fmt, fs, io, path::Path};
+
+/// The maximum distance in user space between a cubic and the quadratics approximating it.
+const CUBIC_TOLERANCE: f32 = 0.05;
+
+const DEFAULT_SIZE: f32 = 100.;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    /// The geometry of an element is invalid, for example if it has infinite coordinates.
+    Path(path::Error),
+    /// The root element is not `<svg>`.
+    NotSvg,
+    /// An attribute that we need could not be parsed.
+    InvalidAttribute {
+        name: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read SVG: {}", e),
+            Self::Xml(e) => write!(f, "Invalid XML: {}", e),
+            Self::Path(e) => write!(f, "Invalid geometry: {}", e),
+            Self::NotSvg => write!(f, "Root element is not <svg>"),
+            Self::InvalidAttribute { name, value } => {
+                write!(f, "Invalid value for attribute `{}`: {:?}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Xml(e) => Some(e),
+            Self::Path(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
+    }
+}
+
+impl From<path::Error> for Error {
+    fn from(e: path::Error) -> Self {
+        Self::Path(e)
+    }
+}
+
+/// Reads and parses the SVG file at `path`.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, Error> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Parses an SVG document.
+pub fn parse(text: &str) -> Result<Scene, Error> {
+    let doc = roxmltree::Document::parse(text)?;
+    let root = doc.root_element();
+
+    if root.tag_name().name() != "svg" {
+        return Err(Error::NotSvg);
+    }
+
+    let view_box = match root.attribute("viewBox") {
+        Some(value) => match &numbers(value)?[..] {
+            &[x, y, width, height] => [x, y, width, height],
+            _ => return Err(invalid("viewBox", value)),
+        },
+        // Relative sizes like `100%` depend on where the document is shown, so we use the default
+        // size for them.
+        None => [
+            0.,
+            0.,
+            root.attribute("width")
+                .and_then(length)
+                .unwrap_or(DEFAULT_SIZE),
+            root.attribute("height")
+                .and_then(length)
+                .unwrap_or(DEFAULT_SIZE),
+        ],
+    };
+
+    let style = Style::default().cascade(&declarations(root));
+    let mut paths = Vec::new();
+
+    for child in root.children() {
+        load_node(child, &style, Matrix3::identity(), &mut paths)?;
+    }
+
+    Ok(Scene::new(view_box, paths))
+}
+
+fn invalid(name: &str, value: &str) -> Error {
+    Error::InvalidAttribute {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// The inherited presentation attributes that we support.
+#[derive(Debug, Clone)]
+struct Style {
+    color: Color,
+    fill: Option<Color>,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: Option<Color>,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    stroke_dasharray: Vec<f32>,
+    stroke_dashoffset: f32,
+    /// The product of the `opacity` of this element and all its ancestors.
+    opacity: f32,
+    blend: BlendMode,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            color: [0., 0., 0., 1.],
+            fill: Some([0., 0., 0., 1.]),
+            fill_opacity: 1.,
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_opacity: 1.,
+            stroke_width: 1.,
+            stroke_dasharray: Vec::new(),
+            stroke_dashoffset: 0.,
+            opacity: 1.,
+            blend: BlendMode::Normal,
+        }
+    }
+}
+
+impl Style {
+    /// Computes the style of an element given the style of its parent and the element's
+    /// `declarations`. Like in a browser, a declaration whose value is invalid or unsupported is
+    /// dropped, so the property keeps the value that it inherited.
+    fn cascade(&self, declarations: &[(&str, &str)]) -> Self {
+        let mut out = self.clone();
+
+        // `currentColor` refers to the value of `color` on the same element, so that must be
+        // resolved first.
+        for (_, value) in declarations.iter().filter(|(name, _)| *name == "color") {
+            out.color = color(value).unwrap_or(out.color);
+        }
+
+        for &(name, value) in declarations {
+            match name {
+                "fill" => out.fill = paint(value, out.color).unwrap_or(out.fill),
+                "stroke" => out.stroke = paint(value, out.color).unwrap_or(out.stroke),
+                "fill-opacity" => out.fill_opacity = opacity(value).unwrap_or(out.fill_opacity),
+                "stroke-opacity" => {
+                    out.stroke_opacity = opacity(value).unwrap_or(out.stroke_opacity)
+                }
+                "opacity" => out.opacity = self.opacity * opacity(value).unwrap_or(1.),
+                "stroke-width" => out.stroke_width = length(value).unwrap_or(out.stroke_width),
+                "stroke-dasharray" => {
+                    if let Some(dash_array) = dash_array(value) {
+                        out.stroke_dasharray = dash_array;
+                    }
+                }
+                "stroke-dashoffset" => {
+                    out.stroke_dashoffset = length(value).unwrap_or(out.stroke_dashoffset)
+                }
+                "mix-blend-mode" => out.blend = blend_mode(value),
+                "fill-rule" => match value {
+                    "nonzero" => out.fill_rule = FillRule::NonZero,
+                    "evenodd" => out.fill_rule = FillRule::EvenOdd,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        out
+    }
+}
+
+/// The presentation attributes of `node` followed by the properties in its `style` attribute,
+/// which take precedence.
+fn declarations<'a>(node: roxmltree::Node<'a, '_>) -> Vec<(&'a str, &'a str)> {
+    let mut out = node
+        .attributes()
+        .iter()
+        .map(|attr| (attr.name(), attr.value()))
+        .collect::<Vec<_>>();
+
+    if let Some(style) = node.attribute("style") {
+        out.extend(style.split(';').filter_map(|decl| {
+            let mut parts = decl.splitn(2, ':');
+
+            Some((parts.next()?.trim(), parts.next()?.trim()))
+        }));
+    }
+
+    out
+}
+
+fn load_node(
+    node: roxmltree::Node,
+    parent_style: &Style,
+    parent_transform: Matrix3<f32>,
+    out: &mut Vec<ScenePath>,
+) -> Result<(), Error> {
+    let name = node.tag_name().name();
+    let drawn = matches!(
+        name,
+        "g" | "svg"
+            | "a"
+            | "switch"
+            | "path"
+            | "rect"
+            | "circle"
+            | "ellipse"
+            | "line"
+            | "polyline"
+            | "polygon"
+    );
+
+    if !node.is_element() || !drawn {
+        return Ok(());
+    }
+
+    let declarations = declarations(node);
+    let display = declarations
+        .iter()
+        .rev()
+        .find(|(name, _)| *name == "display");
+
+    if matches!(display, Some((_, "none"))) {
+        return Ok(());
+    }
+
+    let style = parent_style.cascade(&declarations);
+    let transform = match node.attribute("transform") {
+        Some(value) => {
+            parent_transform * transform(value).ok_or_else(|| invalid("transform", value))?
+        }
+        None => parent_transform,
+    };
+
+    let number = |name| length_attribute(node, name).map(|len| len.unwrap_or(0.));
+    let mut builder = PathBuilder::with_tolerance(CUBIC_TOLERANCE);
+
+    match name {
+        "g" | "svg" | "a" | "switch" => {
+            for child in node.children() {
+                load_node(child, &style, transform, out)?;
+            }
+
+            return Ok(());
+        }
+        "path" => path_data(&mut builder, node.attribute("d").unwrap_or_default()),
+        "rect" => {
+            let (x, y) = (number("x")?, number("y")?);
+            let (width, height) = (number("width")?, number("height")?);
+            let (rx, ry) = match (length_attribute(node, "rx")?, length_attribute(node, "ry")?) {
+                (None, None) => (0., 0.),
+                (Some(r), None) | (None, Some(r)) => (r, r),
+                (Some(rx), Some(ry)) => (rx, ry),
+            };
+
+            builder.rounded_rect(
+                Vector2::new(x, y),
+                Vector2::new(width, height),
+                Vector2::new(rx, ry),
+            );
+        }
+        "circle" => {
+            builder.circle(Vector2::new(number("cx")?, number("cy")?), number("r")?);
+        }
+        "ellipse" => {
+            builder.ellipse(
+                Vector2::new(number("cx")?, number("cy")?),
+                Vector2::new(number("rx")?, number("ry")?),
+            );
+        }
+        "line" => {
+            builder.move_to(Vector2::new(number("x1")?, number("y1")?));
+            builder.line_to(Vector2::new(number("x2")?, number("y2")?));
+        }
+        name @ "polyline" | name @ "polygon" => {
+            let value = node.attribute("points").unwrap_or_default();
+            let points = numbers(value)?;
+            let mut points = points.chunks_exact(2).map(|p| Vector2::new(p[0], p[1]));
+
+            if let Some(first) = points.next() {
+                builder.move_to(first);
+                points.for_each(|p| {
+                    builder.line_to(p);
+                });
+
+                if name == "polygon" {
+                    builder.close();
+                }
+            }
+        }
+        _ => return Ok(()),
+    }
+
+    let path = builder.build()?;
+
+    if path.is_empty() {
+        return Ok(());
+    }
+
+    let with_opacity = |[r, g, b, a]: Color, opacity: f32| [r, g, b, a * opacity * style.opacity];
+    let fill = style.fill.map(|color| Fill {
+        paint: with_opacity(color, style.fill_opacity).into(),
+        rule: style.fill_rule,
+    });
+    let scale = transform.determinant().abs().sqrt();
+    let stroke = style
+        .stroke
+        .filter(|_| style.stroke_width > 0.)
+        .map(|color| Stroke {
+            paint: with_opacity(color, style.stroke_opacity).into(),
+            width: style.stroke_width * scale,
+            dash_array: style.stroke_dasharray.iter().map(|l| l * scale).collect(),
+            dash_offset: style.stroke_dashoffset * scale,
+            trim_start: 0.,
+            trim_end: 1.,
+        });
+
+    if fill.is_none() && stroke.is_none() {
+        return Ok(());
+    }
+
+    out.push(ScenePath {
+        path: path.map_points(|p| (transform * p.extend(1.)).truncate()),
+        fill,
+        stroke,
+        blend: style.blend,
+    });
+
+    Ok(())
+}
+
+/// The current position for relative commands, which is the origin for the first move.
+fn position(builder: &PathBuilder) -> Vector2<f32> {
+    builder.position().unwrap_or_else(|| Vector2::new(0., 0.))
+}
+
+/// Appends the contents of an SVG path's `d` attribute. As required by the spec, malformed
+/// path data is rendered up to the first error.
+fn path_data(builder: &mut PathBuilder, data: &str) {
+    let mut lexer = Lexer::new(data);
+    // For the `S` and `T` commands, which reflect the previous control point
+    let mut last_cubic_control = None;
+    let mut last_quad_control = None;
+
+    while let Some(mut command) = lexer.command() {
+        let relative = command.is_ascii_lowercase();
+
+        // Path data must start with a move
+        if builder.position().is_none() && !command.eq_ignore_ascii_case(&b'M') {
+            return;
+        }
+
+        // Commands other than close path repeat for as long as there are more arguments
+        loop {
+            let origin = if relative {
+                position(builder)
+            } else {
+                Vector2::new(0., 0.)
+            };
+
+            let point =
+                |lexer: &mut Lexer| Some(origin + Vector2::new(lexer.number()?, lexer.number()?));
+
+            let (cubic_control, quad_control) = match command.to_ascii_uppercase() {
+                b'M' => {
+                    let to = match point(&mut lexer) {
+                        Some(to) => to,
+                        None => return,
+                    };
+                    builder.move_to(to);
+                    (None, None)
+                }
+                b'L' => {
+                    let to = match point(&mut lexer) {
+                        Some(to) => to,
+                        None => return,
+                    };
+                    builder.line_to(to);
+                    (None, None)
+                }
+                b'H' => {
+                    let x = match lexer.number() {
+                        Some(x) => x,
+                        None => return,
+                    };
+                    builder.line_to(Vector2::new(origin.x + x, position(builder).y));
+                    (None, None)
+                }
+                b'V' => {
+                    let y = match lexer.number() {
+                        Some(y) => y,
+                        None => return,
+                    };
+                    builder.line_to(Vector2::new(position(builder).x, origin.y + y));
+                    (None, None)
+                }
+                b'C' | b'S' => {
+                    let control1 = if command.eq_ignore_ascii_case(&b'C') {
+                        match point(&mut lexer) {
+                            Some(p) => p,
+                            None => return,
+                        }
+                    } else {
+                        let position = position(builder);
+                        last_cubic_control.map_or(position, |c| 2. * position - c)
+                    };
+                    let (control2, to) = match (point(&mut lexer), point(&mut lexer)) {
+                        (Some(control2), Some(to)) => (control2, to),
+                        _ => return,
+                    };
+                    builder.cubic_to(control1, control2, to);
+                    (Some(control2), None)
+                }
+                b'Q' | b'T' => {
+                    let control = if command.eq_ignore_ascii_case(&b'Q') {
+                        match point(&mut lexer) {
+                            Some(p) => p,
+                            None => return,
+                        }
+                    } else {
+                        let position = position(builder);
+                        last_quad_control.map_or(position, |c| 2. * position - c)
+                    };
+                    let to = match point(&mut lexer) {
+                        Some(to) => to,
+                        None => return,
+                    };
+                    builder.quad_to(control, to);
+                    (None, Some(control))
+                }
+                b'A' => {
+                    let args = (|| {
+                        Some((
+                            Vector2::new(lexer.number()?, lexer.number()?),
+                            lexer.number()?,
+                            lexer.flag()?,
+                            lexer.flag()?,
+                            point(&mut lexer)?,
+                        ))
+                    })();
+                    let (radii, x_rotation, large_arc, sweep, to) = match args {
+                        Some(args) => args,
+                        None => return,
+                    };
+                    builder.arc_to(radii, x_rotation, large_arc, sweep, to);
+                    (None, None)
+                }
+                b'Z' => {
+                    builder.close();
+                    last_cubic_control = None;
+                    last_quad_control = None;
+                    break;
+                }
+                _ => return,
+            };
+
+            last_cubic_control = cubic_control;
+            last_quad_control = quad_control;
+
+            // Extra coordinates after a move are treated as lines
+            if command.eq_ignore_ascii_case(&b'M') {
+                command = if relative { b'l' } else { b'L' };
+            }
+
+            if !lexer.has_number() {
+                break;
+            }
+        }
+    }
+}
+
+/// A tokenizer for path data and other whitespace- or comma-separated lists of numbers.
+struct Lexer<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            rest: data.as_bytes(),
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some((c, rest)) = self.rest.split_first() {
+            if c.is_ascii_whitespace() || *c == b',' {
+                self.rest = rest;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+
+        let (&c, rest) = self.rest.split_first()?;
+
+        if c.is_ascii_alphabetic() {
+            self.rest = rest;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+
+        matches!(self.rest.first(), Some(c) if c.is_ascii_digit() || b"+-.".contains(c))
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+
+        let digits =
+            |s: &[u8], i: usize| i + s[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+
+        let s = self.rest;
+        let mut i = 0;
+
+        if matches!(s.first(), Some(b'+') | Some(b'-')) {
+            i += 1;
+        }
+
+        i = digits(s, i);
+
+        // Numbers like `.5.5` are two numbers, so only the first `.` is part of this one
+        if s.get(i) == Some(&b'.') {
+            i = digits(s, i + 1);
+        }
+
+        if matches!(s.get(i), Some(b'e') | Some(b'E')) {
+            let mut j = i + 1;
+
+            if matches!(s.get(j), Some(b'+') | Some(b'-')) {
+                j += 1;
+            }
+
+            if matches!(s.get(j), Some(c) if c.is_ascii_digit()) {
+                i = digits(s, j);
+            }
+        }
+
+        let out = finite(std::str::from_utf8(&s[..i]).ok()?)?;
+        self.rest = &s[i..];
+
+        Some(out)
+    }
+
+    /// Arc flags are a single character and don't need to be separated from what follows them.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+
+        let (&c, rest) = self.rest.split_first()?;
+        let out = match c {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.rest = rest;
+
+        Some(out)
+    }
+}
+
+fn numbers(value: &str) -> Result<Vec<f32>, Error> {
+    let mut lexer = Lexer::new(value);
+    let mut out = Vec::new();
+
+    while lexer.has_number() {
+        out.push(lexer.number().ok_or_else(|| invalid("number", value))?);
+    }
+
+    lexer.skip_separators();
+
+    if lexer.rest.is_empty() {
+        Ok(out)
+    } else {
+        Err(invalid("number", value))
+    }
+}
+
+/// Parses a number, which has to be finite, so `nan`, `inf` and values that overflow are invalid.
+fn finite(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+/// Parses a length. Only absolute lengths in user units are supported, with an optional `px`.
+fn length(value: &str) -> Option<f32> {
+    let value = value.trim();
+
+    finite(value.strip_suffix("px").unwrap_or(value).trim())
+}
+
+/// Parses a `stroke-dasharray` value, where `none` is an empty list. Negative lengths are
+/// invalid.
+fn dash_array(value: &str) -> Option<Vec<f32>> {
+    if value.trim() == "none" {
+        return Some(Vec::new());
+    }
+
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| length(part).filter(|&l| l >= 0.))
+        .collect()
+}
+
+fn length_attribute(node: roxmltree::Node, name: &str) -> Result<Option<f32>, Error> {
+    node.attribute(name)
+        .map(|value| length(value).ok_or_else(|| invalid(name, value)))
+        .transpose()
+}
+
+fn opacity(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => finite(percent)? / 100.,
+        None => finite(value)?,
+    };
+
+    Some(opacity.clamp(0., 1.))
+}
+
+/// Parses `mix-blend-mode`. The modes that can't be done with fixed-function blending, such as
+/// `overlay`, are drawn normally.
+fn blend_mode(value: &str) -> BlendMode {
+    match value {
+        "multiply" => BlendMode::Multiply,
+        "screen" => BlendMode::Screen,
+        "plus-lighter" => BlendMode::Add,
+        _ => BlendMode::Normal,
+    }
+}
+
+/// Parses a `fill` or `stroke` value, where `Some(None)` is `none`. Paint servers like
+/// gradients are not supported, so we use their fallback colour if there is one.
+fn paint(value: &str, current_color: Color) -> Option<Option<Color>> {
+    let value = value.trim();
+
+    if value.starts_with("url(") {
+        let fallback = value[value.find(')')? + 1..].trim();
+
+        return if fallback.is_empty() {
+            Some(None)
+        } else {
+            paint(fallback, current_color)
+        };
+    }
+
+    match value {
+        "none" => Some(None),
+        "currentColor" => Some(Some(current_color)),
+        _ => color(value).map(Some),
+    }
+}
+
+fn color(value: &str) -> Option<Color> {
+    let value = value.trim();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+        let [r, g, b] = match hex.len() {
+            3 => [digit(0)? * 17, digit(1)? * 17, digit(2)? * 17],
+            6 => [byte(0)?, byte(2)?, byte(4)?],
+            _ => return None,
+        };
+
+        return Some([r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.]);
+    }
+
+    let functional = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("));
+
+    if let Some(args) = functional {
+        let args = args
+            .strip_suffix(')')?
+            .split(',')
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        if args.len() < 3 || args.len() > 4 {
+            return None;
+        }
+
+        let channel = |arg: &str| match arg.strip_suffix('%') {
+            Some(percent) => finite(percent).map(|p| p / 100.),
+            None => finite(arg).map(|c| c / 255.),
+        };
+        let alpha = match args.get(3) {
+            Some(alpha) => finite(alpha)?,
+            None => 1.,
+        };
+
+        return Some([
+            channel(args[0])?.clamp(0., 1.),
+            channel(args[1])?.clamp(0., 1.),
+            channel(args[2])?.clamp(0., 1.),
+            alpha.clamp(0., 1.),
+        ]);
+    }
+
+    let [r, g, b]: [u8; 3] = match &*value.to_ascii_lowercase() {
+        "transparent" => return Some([0., 0., 0., 0.]),
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        _ => return None,
+    };
+
+    Some([r as f32 / 255., g as f32 / 255., b as f32 / 255., 1.])
+}
+
+/// Parses a `transform` attribute into an affine transformation matrix.
+fn transform(value: &str) -> Option<Matrix3<f32>> {
+    let mut out = Matrix3::identity();
+    let mut rest = value.trim();
+
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let close = rest.find(')')?;
+        let name = rest[..open].trim();
+        let args = numbers(&rest[open + 1..close]).ok()?;
+
+        // Note that `Matrix3::new` takes its arguments column-by-column
+        let matrix = match (name, &args[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => Matrix3::new(a, b, 0., c, d, 0., e, f, 1.),
+            ("translate", &[x]) => Matrix3::new(1., 0., 0., 0., 1., 0., x, 0., 1.),
+            ("translate", &[x, y]) => Matrix3::new(1., 0., 0., 0., 1., 0., x, y, 1.),
+            ("scale", &[s]) => Matrix3::new(s, 0., 0., 0., s, 0., 0., 0., 1.),
+            ("scale", &[x, y]) => Matrix3::new(x, 0., 0., 0., y, 0., 0., 0., 1.),
+            ("rotate", &[angle]) => rotation(angle),
+            ("rotate", &[angle, x, y]) => {
+                let to = Matrix3::new(1., 0., 0., 0., 1., 0., x, y, 1.);
+                let from = Matrix3::new(1., 0., 0., 0., 1., 0., -x, -y, 1.);
+
+                to * rotation(angle) * from
+            }
+            ("skewX", &[angle]) => {
+                Matrix3::new(1., 0., 0., angle.to_radians().tan(), 1., 0., 0., 0., 1.)
+            }
+            ("skewY", &[angle]) => {
+                Matrix3::new(1., angle.to_radians().tan(), 0., 0., 1., 0., 0., 0., 1.)
+            }
+            _ => return None,
+        };
+
+        out = out * matrix;
+        rest = rest[close + 1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    Some(out)
+}
+
+fn rotation(degrees: f32) -> Matrix3<f32> {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    Matrix3::new(cos, sin, 0., -sin, cos, 0., 0., 0., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> Scene {
+        parse(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">{}</svg>"#,
+            body
+        ))
+        .unwrap()
+    }
+
+    fn fill(path: &ScenePath) -> Option<Color> {
+        path.fill
+            .as_ref()
+            .map(|fill| fill.paint.as_solid().unwrap())
+    }
+
+    #[test]
+    fn shapes() {
+        let scene = svg(r#"
+            <path d="M0 0 L1 1"/>
+            <rect width="1" height="1"/>
+            <circle r="1"/>
+            <ellipse rx="1" ry="2"/>
+            <line x2="1" y2="1"/>
+            <polyline points="0 0 1 1 2 0"/>
+            <polygon points="0 0 1 1 2 0"/>
+            <text>Skipped</text>
+        "#);
+
+        assert_eq!(scene.view_box, [0., 0., 10., 10.]);
+        assert_eq!(scene.paths().len(), 7);
+    }
+
+    #[test]
+    fn not_svg() {
+        assert!(matches!(parse("<html/>"), Err(Error::NotSvg)));
+    }
+
+    #[test]
+    fn size_without_view_box() {
+        let scene = parse(r#"<svg width="20" height="100%"/>"#).unwrap();
+
+        assert_eq!(scene.view_box, [0., 0., 20., DEFAULT_SIZE]);
+    }
+
+    #[test]
+    fn cascade() {
+        let scene = svg(r#"
+            <g fill="red" stroke="blue" style="stroke-width: 2" opacity="0.5">
+                <rect width="1" height="1" fill-opacity="50%"/>
+                <rect width="1" height="1" style="fill: currentColor" color="lime"/>
+            </g>
+        "#);
+
+        assert_eq!(fill(&scene.paths()[0]), Some([1., 0., 0., 0.25]));
+        assert_eq!(fill(&scene.paths()[1]), Some([0., 1., 0., 0.5]));
+        assert_eq!(scene.paths()[0].stroke.as_ref().unwrap().width, 2.);
+    }
+
+    #[test]
+    fn invalid_declarations_inherit() {
+        let scene = svg(r#"
+            <g fill="red" stroke="blue" stroke-width="2" fill-rule="evenodd">
+                <rect width="1" height="1" fill="rebeccapurple" stroke="hsl(0, 0%, 0%)"/>
+                <rect width="1" height="1" fill="inherit" style="stroke: rgb(0 0 0)"/>
+                <rect width="1" height="1" fill="rgba(0, 0, 0, 50%)" stroke-width="1em"/>
+                <rect width="1" height="1" stroke-width="12pt" fill-rule="winding"/>
+                <rect width="1" height="1" stroke-width="50%" opacity="half"/>
+            </g>
+        "#);
+
+        assert_eq!(scene.paths().len(), 5);
+
+        for path in scene.paths() {
+            let stroke = path.stroke.as_ref().unwrap();
+
+            assert_eq!(fill(path), Some([1., 0., 0., 1.]));
+            assert_eq!(path.fill.as_ref().unwrap().rule, FillRule::EvenOdd);
+            assert_eq!(stroke.paint.as_solid(), Some([0., 0., 1., 1.]));
+            assert_eq!(stroke.width, 2.);
+        }
+    }
+
+    #[test]
+    fn non_finite_numbers_inherit() {
+        let scene = svg(r#"
+            <g fill="red" stroke="blue" stroke-width="2" opacity="0.5">
+                <rect width="1" height="1" opacity="nan" stroke-width="nan" fill="rgb(nan, 0, 0)"/>
+                <rect width="1" height="1" opacity="inf" stroke-width="inf" fill="rgb(0, inf, 0)"/>
+                <rect width="1" height="1" opacity="1e39" stroke-width="1e39"
+                    fill="rgba(0, 0, 0, 1e39)" stroke-opacity="-inf%"/>
+            </g>
+        "#);
+
+        assert_eq!(scene.paths().len(), 3);
+
+        for path in scene.paths() {
+            let stroke = path.stroke.as_ref().unwrap();
+
+            assert_eq!(fill(path), Some([1., 0., 0., 0.5]));
+            assert_eq!(stroke.paint.as_solid(), Some([0., 0., 1., 0.5]));
+            assert_eq!(stroke.width, 2.);
+        }
+    }
+
+    #[test]
+    fn display_none() {
+        let scene = svg(r#"
+            <rect width="1" height="1" display="none"/>
+            <g style="display: none"><rect width="1" height="1"/></g>
+            <rect width="1" height="1" display="none" style="display: inline"/>
+        "#);
+
+        assert_eq!(scene.paths().len(), 1);
+    }
+
+    #[test]
+    fn transform_and_fill_none() {
+        let scene = svg(r#"
+            <g transform="translate(1, 2) scale(2)">
+                <line x2="1" y2="1" stroke="black" fill="none"/>
+            </g>
+        "#);
+        let path = &scene.paths()[0];
+        let bounds = path.path.bounds().unwrap();
+
+        assert_eq!(path.fill, None);
+        assert_eq!(path.stroke.as_ref().unwrap().width, 2.);
+        assert_eq!((bounds.min.x, bounds.min.y), (1., 2.));
+        assert_eq!((bounds.max.x, bounds.max.y), (3., 4.));
+    }
+
+    #[test]
+    fn malformed_path_data() {
+        let scene = svg(r#"<path d="M0 0 L1 1 L2 X 3 3"/><path d="L1 1"/>"#);
+
+        assert_eq!(scene.paths().len(), 1);
+    }
+
+    #[test]
+    fn invalid_geometry() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect width="x"/></svg>"#;
+
+        assert!(matches!(
+            parse(text),
+            Err(Error::InvalidAttribute { name, .. }) if name == "width"
+        ));
+    }
+}
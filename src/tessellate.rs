@@ -0,0 +1,569 @@
+//! # Tessellation
+//!
+//! This turns paths into the triangles consumed by the `curve` and `fill` pipelines.
+//!
+//! Degenerate input never produces NaN vertices. Quadratics whose control point is collinear with
+//! their endpoints (including when it coincides with one of them) are stroked as the one or two
+//! straight lines that they trace out, and lines and curves of zero length are skipped. `validate`
+//! can be used to check the output, and `Options::validate` does so for every draw.
+
+use crate::{
+    measure::SegmentLength,
+    path::{Path, Segment},
+    pipelines::curve,
+};
+use cgmath::{InnerSpace, Vector2};
+use std::{fmt, num::NonZeroU8};
+
+/// A quadratic bezier as `(start, control, end)`.
+pub type Quadratic = (Vector2<f32>, Vector2<f32>, Vector2<f32>);
+
+/// The control point of a quadratic is treated as collinear with its endpoints if the area of
+/// the control polygon is less than this fraction of the square of its longest side. This is
+/// roughly the sine of the angle that the control point is off the line.
+const COLLINEAR_EPSILON: f32 = 1e-5;
+
+/// How far the inner offset of a stroke can be from the curve, as a fraction of the radius of
+/// curvature. Any further and the offset curve folds over itself.
+const MAX_INNER_OFFSET: f32 = 0.9;
+
+/// The maximum number of times each piece of a stroke is halved where the curve is too tight
+/// for the stroke's width.
+const MAX_CURVATURE_SPLITS: u32 = 3;
+
+/// A vertex with a NaN or infinite component, as found by `validate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidVertex {
+    pub index: usize,
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub sign: f32,
+}
+
+impl fmt::Display for InvalidVertex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Vertex {} is not finite (pos: {:?}, uv: {:?}, sign: {})",
+            self.index, self.pos, self.uv, self.sign
+        )
+    }
+}
+
+impl std::error::Error for InvalidVertex {}
+
+/// Checks that every component of every vertex is finite.
+pub fn validate(vertices: &[curve::Vertex]) -> Result<(), InvalidVertex> {
+    let is_finite = |v: &curve::Vertex| {
+        v.pos
+            .iter()
+            .chain(&v.uv)
+            .chain(Some(&v.sign))
+            .all(|c| c.is_finite())
+    };
+
+    match vertices.iter().position(|v| !is_finite(v)) {
+        Some(index) => Err(InvalidVertex {
+            index,
+            pos: vertices[index].pos,
+            uv: vertices[index].uv,
+            sign: vertices[index].sign,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The unit vector pointing along `v`, or `None` if `v` is too short to have a direction.
+fn direction(v: Vector2<f32>) -> Option<Vector2<f32>> {
+    let out = v.normalize();
+
+    if out.x.is_finite() && out.y.is_finite() {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+/// If the control point of `quadratic` is collinear with its endpoints, returns the straight
+/// lines that the curve traces out as `(start, end)`. This is a single line unless the control
+/// point is beyond one of the endpoints, in which case the curve reaches a cusp and doubles back
+/// on itself. Returns `None` if the quadratic is a proper curve.
+fn straighten((start, control, end): Quadratic) -> Option<Vec<(Vector2<f32>, Vector2<f32>)>> {
+    let size = (control - start)
+        .magnitude()
+        .max((end - start).magnitude())
+        .max((end - control).magnitude());
+
+    if (control - start).perp_dot(end - start).abs() > COLLINEAR_EPSILON * size * size {
+        return None;
+    }
+
+    // The cusp is where the derivative is zero. This is NaN if the control point is exactly
+    // halfway between the endpoints, which is a single line.
+    let second_derivative = start - 2. * control + end;
+    let t = (start - control).dot(second_derivative) / second_derivative.magnitude2();
+
+    let lines = if t > 0. && t < 1. {
+        let cusp = point_on_quadratic(start, control, end, t);
+
+        vec![(start, cusp), (cusp, end)]
+    } else {
+        vec![(start, end)]
+    };
+
+    Some(lines)
+}
+
+/// Calculates a quadratic bezier that passes through the start and end
+/// point at t=0 and t=1 respectively, and passes through the midpoint
+/// at t=midpoint_t. It doesn't take gradient into account, which leads
+/// to discontinuities and should be addressed.
+fn calc_quadratic(
+    start: Vector2<f32>,
+    mid: Vector2<f32>,
+    end: Vector2<f32>,
+    midpoint_t: f32,
+) -> Quadratic {
+    let t = midpoint_t;
+
+    (
+        start,
+        (mid - t.powi(2) * end - (1. - t).powi(2) * start) / (2. * t * (1. - t)),
+        end,
+    )
+}
+
+pub fn point_on_quadratic(
+    start: Vector2<f32>,
+    control: Vector2<f32>,
+    end: Vector2<f32>,
+    t: f32,
+) -> Vector2<f32> {
+    start * (1. - t).powi(2) + 2. * control * t * (1. - t) + end * t.powi(2)
+}
+
+fn normal_at(
+    start: Vector2<f32>,
+    control: Vector2<f32>,
+    end: Vector2<f32>,
+    t: f32,
+) -> Vector2<f32> {
+    // Basic derivation of bezier formula. This is only zero for collinear control points, which
+    // `straighten` removes, but fall back to the direction of the chord in case of rounding error.
+    let tangent = direction(2. * (1. - t) * (control - start) + 2. * t * (end - control))
+        .or_else(|| direction(end - start))
+        .unwrap_or_else(Vector2::unit_x);
+
+    // Rotate 90 degrees
+    Vector2::from((-tangent.y, tangent.x))
+}
+
+/// Triangulate a quad. This function is extremely bad and
+/// should handle automatically generating correct vertices
+/// for both clockwise and counter-clockwise quads.
+fn quad<T: Copy>(input: [T; 4]) -> [T; 6] {
+    [input[0], input[2], input[3], input[3], input[1], input[0]]
+}
+
+/// The triangles of a single quadratic, in the order expected by the `uv` coordinates that the
+/// curve shaders use for the `u*u - v` test.
+fn curve_triangle((start, control, end): Quadratic, sign: f32) -> [curve::Vertex; 3] {
+    [
+        curve::Vertex {
+            pos: start.into(),
+            uv: [0., 0.],
+            sign,
+        },
+        curve::Vertex {
+            pos: control.into(),
+            uv: [0.5, 0.],
+            sign,
+        },
+        curve::Vertex {
+            pos: end.into(),
+            uv: [1., 1.],
+            sign,
+        },
+    ]
+}
+
+/// `curve_triangle` grown by `feather` on every side, so that `curve.frag` can fade out the
+/// outside of the curve where it runs along the edges of the triangle. This is the rectangle
+/// around the triangle lined up with its chord, with `uv` extended across it the same way that
+/// it is interpolated across the triangle. Falls back to the plain triangle if it's degenerate.
+fn feathered_curve_triangle(quadratic: Quadratic, sign: f32, feather: f32) -> Vec<curve::Vertex> {
+    let (start, control, end) = quadratic;
+    let triangle = curve_triangle(quadratic, sign);
+    let along = match direction(end - start) {
+        Some(along) => along,
+        None => return triangle.to_vec(),
+    };
+    let across = Vector2::new(-along.y, along.x);
+    let area = (control - start).perp_dot(end - start);
+
+    if feather <= 0. || !area.is_normal() {
+        return triangle.to_vec();
+    }
+
+    let project = |p: Vector2<f32>| ((p - start).dot(along), (p - start).dot(across));
+    let (lo, hi) = [start, control, end].iter().map(|&p| project(p)).fold(
+        (
+            (f32::INFINITY, f32::INFINITY),
+            (f32::NEG_INFINITY, f32::NEG_INFINITY),
+        ),
+        |(lo, hi), (s, t)| ((lo.0.min(s), lo.1.min(t)), (hi.0.max(s), hi.1.max(t))),
+    );
+    let vertex = |s: f32, t: f32| {
+        let p = start + along * s + across * t;
+        // The barycentric weights of `control` and `end`, which have `uv` of `[0.5, 0]` and
+        // `[1, 1]` while `start` has `[0, 0]`
+        let w1 = (p - start).perp_dot(end - start) / area;
+        let w2 = (control - start).perp_dot(p - start) / area;
+
+        curve::Vertex {
+            pos: p.into(),
+            uv: [0.5 * w1 + w2, w2],
+            sign,
+        }
+    };
+
+    quad([
+        vertex(lo.0 - feather, hi.1 + feather),
+        vertex(hi.0 + feather, hi.1 + feather),
+        vertex(lo.0 - feather, lo.1 - feather),
+        vertex(hi.0 + feather, lo.1 - feather),
+    ])
+    .to_vec()
+}
+
+/// A piece of a stroke, covering either a line or part of one of the quadratics of the path
+/// being stroked.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StrokeSegment {
+    /// A straight line, which only needs a single quad drawn with `sign == 0`.
+    Line { quad: [Vector2<f32>; 4] },
+    Curve {
+        /// The offset curve that is drawn with `sign == 1`.
+        outer: Quadratic,
+        /// The offset curve that is drawn with `sign == -1`.
+        inner: Quadratic,
+        /// The quads that fill the space between the two offset curves, drawn with `sign == 0`.
+        quads: [[Vector2<f32>; 4]; 2],
+    },
+}
+
+impl StrokeSegment {
+    /// The triangles that the quads are split into.
+    pub fn quad_triangles(&self) -> impl Iterator<Item = [Vector2<f32>; 3]> {
+        let quads = match self {
+            Self::Line { quad } => vec![*quad],
+            Self::Curve { quads, .. } => quads.to_vec(),
+        };
+
+        quads.into_iter().flat_map(|input| {
+            let out = quad(input);
+
+            vec![[out[0], out[1], out[2]], [out[3], out[4], out[5]]]
+        })
+    }
+
+    /// The offset curves on the left and right of the piece, looking along the path, as
+    /// `(left, right)`. Both are in the direction of the path.
+    pub fn sides(&self) -> (Segment, Segment) {
+        match *self {
+            Self::Line { quad } => (
+                Segment::Line(quad[0], quad[1]),
+                Segment::Line(quad[2], quad[3]),
+            ),
+            Self::Curve { outer, inner, .. } => {
+                // The inner curve is on whichever side the piece curves towards
+                let tangent = direction(outer.1 - outer.0).unwrap_or(outer.2 - outer.0);
+
+                if tangent.perp_dot(inner.0 - outer.0) > 0. {
+                    (Segment::Quadratic(inner), Segment::Quadratic(outer))
+                } else {
+                    (Segment::Quadratic(outer), Segment::Quadratic(inner))
+                }
+            }
+        }
+    }
+
+    /// The vertices for the `curve` pipeline. The sides of a line are pushed out by `feather`,
+    /// so that `curve.frag` has room to fade out the edges, and `v` goes from -1 on one side of
+    /// the stroke to 1 on the other. The quads of a curve are inside the stroke, so they have
+    /// `uv == 0` and are never faded.
+    pub fn vertices(&self, feather: f32) -> impl Iterator<Item = curve::Vertex> {
+        let vertex = |p: Vector2<f32>, across: f32| curve::Vertex {
+            pos: p.into(),
+            uv: [0., across],
+            sign: 0.,
+        };
+
+        match *self {
+            Self::Line { quad: q } => {
+                let normal = (q[0] - q[2]) / 2.;
+                let width = normal.magnitude();
+                let (offset, across) = if width > 0. {
+                    (normal * feather / width, 1. + feather / width)
+                } else {
+                    (Vector2::new(0., 0.), 0.)
+                };
+
+                quad([
+                    vertex(q[0] + offset, across),
+                    vertex(q[1] + offset, across),
+                    vertex(q[2] - offset, -across),
+                    vertex(q[3] - offset, -across),
+                ])
+                .to_vec()
+            }
+            Self::Curve { outer, inner, .. } => feathered_curve_triangle(outer, 1., feather)
+                .into_iter()
+                .chain(feathered_curve_triangle(inner, -1., feather))
+                .chain(
+                    self.quad_triangles()
+                        .flat_map(|triangle| triangle.to_vec())
+                        .map(|p| vertex(p, 0.)),
+                )
+                .collect(),
+        }
+        .into_iter()
+    }
+}
+
+/// The stroke of a straight line, which is a single quad covering `width` either side of it in
+/// the same order as the quads of a curve. Lines of zero length have no stroke.
+fn line_segment(start: Vector2<f32>, end: Vector2<f32>, width: f32) -> Option<StrokeSegment> {
+    let direction = direction(end - start)?;
+    let normal = Vector2::new(-direction.y, direction.x) * width;
+
+    Some(StrokeSegment::Line {
+        quad: [start + normal, end + normal, start - normal, end - normal],
+    })
+}
+
+/// How the pieces of a quadratic are spaced when it is split up for stroking.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Spacing {
+    /// Equal steps in `t`. This is cheaper, but gives shorter pieces where the curve moves more
+    /// slowly, which is usually around its tightest point.
+    #[default]
+    Parameter,
+    /// Equal lengths along the curve.
+    ArcLength,
+}
+
+/// Splits each quadratic of `path` into `num_segments` pieces and calculates the curves that
+/// are offset by `width` either side of each piece. Lines, and quadratics that are really
+/// lines, are offset directly and are never split.
+pub fn decompose_stroke(
+    path: &Path,
+    width: f32,
+    num_segments: NonZeroU8,
+    spacing: Spacing,
+) -> impl Iterator<Item = StrokeSegment> + '_ {
+    path.segments()
+        .flat_map(move |segment| decompose_segment(segment, width, num_segments, spacing))
+}
+
+/// `decompose_stroke` for a single segment. The pieces are in the direction of the segment, and
+/// the offset curves of consecutive pieces of a quadratic meet exactly.
+pub fn decompose_segment(
+    segment: Segment,
+    width: f32,
+    num_segments: NonZeroU8,
+    spacing: Spacing,
+) -> Vec<StrokeSegment> {
+    match segment {
+        Segment::Line(start, end) => line_segment(start, end, width).into_iter().collect(),
+        Segment::Quadratic(quadratic) => match straighten(quadratic) {
+            Some(lines) => lines
+                .into_iter()
+                .filter_map(|(start, end)| line_segment(start, end, width))
+                .collect(),
+            None => decompose_quadratic(quadratic, width, num_segments, spacing).collect(),
+        },
+    }
+}
+
+fn decompose_quadratic(
+    quadratic: Quadratic,
+    width: f32,
+    num_segments: NonZeroU8,
+    spacing: Spacing,
+) -> impl Iterator<Item = StrokeSegment> {
+    let (start, control, end) = quadratic;
+    let dt = (num_segments.get() as f32).recip();
+    let measured = match spacing {
+        Spacing::Parameter => None,
+        Spacing::ArcLength => Some(SegmentLength::new(Segment::Quadratic(quadratic))),
+    };
+    let t_at = |fraction: f32| {
+        measured.as_ref().map_or(fraction, |measured| {
+            measured.t_at(fraction * measured.length())
+        })
+    };
+
+    // The inner offset has to be on the concave side of the curve, so flip the normals of
+    // clockwise curves to point towards the centre of curvature.
+    let orientation = if (control - start).perp_dot(end - control) < 0. {
+        -1.
+    } else {
+        1.
+    };
+
+    let mut pieces = Vec::new();
+    for i in 0..num_segments.get() {
+        split_for_curvature(
+            quadratic,
+            width,
+            (t_at(i as f32 * dt), t_at((i + 1) as f32 * dt)),
+            MAX_CURVATURE_SPLITS,
+            &mut pieces,
+        );
+    }
+
+    // We take 3 points from each piece, as the end of 1 curve is the start of the next but the
+    // control points are not shared.
+    pieces.into_iter().map(move |(a, c)| {
+        let b = (a + c) / 2.;
+
+        let (a_point, b_point, c_point) = (
+            point_on_quadratic(start, control, end, a),
+            point_on_quadratic(start, control, end, b),
+            point_on_quadratic(start, control, end, c),
+        );
+        let (a_norm, b_norm, c_norm) = (
+            normal_at(start, control, end, a) * orientation,
+            normal_at(start, control, end, b) * orientation,
+            normal_at(start, control, end, c) * orientation,
+        );
+
+        // Past the centre of curvature the inner offset folds back on itself, so it is pulled
+        // in to stay short of it. This makes the inside of very tight curves slightly thinner
+        // than the rest of the stroke, but keeps the offset curve well-formed.
+        let inner_width = |t: f32| width.min(MAX_INNER_OFFSET / curvature(quadratic, t));
+        let (a_width, b_width, c_width) = (inner_width(a), inner_width(b), inner_width(c));
+
+        // TODO: This leads to weird C1 discontinuities, so we might want to solve for
+        //       gradient at the start and end and minimise positional error, or have
+        //       some other way to normalise the gradient between segments to maintain
+        //       C1 continuity.
+        let perc = (b - a) / (c - a);
+        let inner = calc_quadratic(
+            a_point + a_norm * a_width,
+            b_point + b_norm * b_width,
+            c_point + c_norm * c_width,
+            perc,
+        );
+
+        let outer = calc_quadratic(
+            a_point - a_norm * width,
+            b_point - b_norm * width,
+            c_point - c_norm * width,
+            perc,
+        );
+
+        let outer_mid = point_on_quadratic(outer.0, outer.1, outer.2, 0.5);
+
+        StrokeSegment::Curve {
+            outer,
+            inner,
+            quads: [
+                [inner.0, inner.1, outer.0, outer_mid],
+                [inner.1, inner.2, outer_mid, outer.2],
+            ],
+        }
+    })
+}
+
+/// The unsigned curvature of `quadratic` at `t`, which is the reciprocal of its radius of
+/// curvature.
+fn curvature((start, control, end): Quadratic, t: f32) -> f32 {
+    let first_derivative = 2. * ((1. - t) * (control - start) + t * (end - control));
+    let second_derivative = 2. * (start - 2. * control + end);
+
+    (first_derivative.perp_dot(second_derivative) / first_derivative.magnitude().powi(3)).abs()
+}
+
+/// The highest curvature of `quadratic` between `from` and `to`. The curvature of a quadratic
+/// peaks where it's moving slowest, and falls off either side of that.
+fn max_curvature(quadratic: Quadratic, (from, to): (f32, f32)) -> f32 {
+    let (start, control, end) = quadratic;
+    let second_derivative = start - 2. * control + end;
+    let peak = (start - control).dot(second_derivative) / second_derivative.magnitude2();
+
+    curvature(quadratic, peak.max(from).min(to))
+}
+
+/// Adds the range `(from, to)` of `quadratic` to `out`, halving it up to `depth` times for as long
+/// as the inner offset of a stroke `width` wide would need to be pulled in somewhere in the range.
+/// Smaller pieces follow the pulled-in offset more closely.
+fn split_for_curvature(
+    quadratic: Quadratic,
+    width: f32,
+    (from, to): (f32, f32),
+    depth: u32,
+    out: &mut Vec<(f32, f32)>,
+) {
+    if depth > 0 && width * max_curvature(quadratic, (from, to)) > MAX_INNER_OFFSET {
+        let mid = (from + to) / 2.;
+
+        split_for_curvature(quadratic, width, (from, mid), depth - 1, out);
+        split_for_curvature(quadratic, width, (mid, to), depth - 1, out);
+    } else {
+        out.push((from, to));
+    }
+}
+
+/// Generates the vertices for the `curve` pipeline that stroke `path` with a line that extends
+/// `width` either side of the curve. `feather` is how far past the edge of the stroke the
+/// anti-aliasing in `curve.frag` can reach, which should be about a pixel.
+pub fn stroke(
+    path: &Path,
+    width: f32,
+    num_segments: NonZeroU8,
+    spacing: Spacing,
+    feather: f32,
+) -> Vec<curve::Vertex> {
+    decompose_stroke(path, width, num_segments, spacing)
+        .flat_map(|segment| segment.vertices(feather))
+        .collect()
+}
+
+/// Generates the vertices for the `fill` pipeline that fill the area enclosed by `path`. Open
+/// subpaths are implicitly closed with a straight line.
+///
+/// Each subpath is drawn as a triangle fan around its first point, with one extra triangle per
+/// quadratic to add or remove the area between the curve and its chord. Lines only need their
+/// triangle in the fan. The `fill` pipeline accumulates the winding number of each pixel in the
+/// alpha channel by adding or subtracting depending on which way the triangle faces, and the
+/// fill rule is applied when blitting. The fan has a `sign` of 0, so that `fill.frag` only fades
+/// out the edges of the curve triangles.
+pub fn fill(path: &Path) -> Vec<curve::Vertex> {
+    path.subpaths()
+        .iter()
+        .flat_map(|subpath| {
+            let origin = subpath.start();
+
+            subpath.segments().iter().flat_map(move |segment| {
+                let (start, end) = (segment.start(), segment.end());
+
+                let mut out = [origin, start, end]
+                    .iter()
+                    .map(|p| curve::Vertex {
+                        pos: (*p).into(),
+                        uv: Default::default(),
+                        sign: 0.,
+                    })
+                    .collect::<Vec<_>>();
+
+                if let Segment::Quadratic(quadratic) = *segment {
+                    out.extend_from_slice(&curve_triangle(quadratic, 1.));
+                }
+
+                out
+            })
+        })
+        .collect()
+}
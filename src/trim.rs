@@ -0,0 +1,167 @@
+//! # Trimming
+//!
+//! Cuts a path down to the part between two fractions of its length, for animating a stroke
+//! being drawn. Lengths are measured over every subpath in order, as with `PathMeasure`, and
+//! segments are cut at the exact arc length with `Segment::slice`. The result is an ordinary
+//! path, so it is tessellated like any other.
+
+use crate::{
+    measure::SegmentLength,
+    path::{Path, PathBuilder},
+};
+
+/// The part of `path` from `start` to `end`, as fractions of its total length from 0 to 1. Both
+/// are clamped to that range, and the path is empty if `start` is not before `end`.
+///
+/// Subpaths that are cut stay connected but are left open, while closed subpaths that are kept
+/// whole stay closed.
+pub fn trim(path: &Path, start: f32, end: f32) -> Path {
+    let clamp = |fraction: f32| fraction.clamp(0., 1.);
+    let (start, end) = (clamp(start), clamp(end));
+
+    if start <= 0. && end >= 1. {
+        return path.clone();
+    }
+
+    let measured = path
+        .subpaths()
+        .iter()
+        .map(|subpath| {
+            subpath
+                .segments()
+                .iter()
+                .map(|&segment| SegmentLength::new(segment))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let total = measured
+        .iter()
+        .flatten()
+        .map(SegmentLength::length)
+        .sum::<f32>();
+    let (from, to) = (start * total, end * total);
+
+    let mut builder = PathBuilder::new();
+    let mut position = 0.;
+
+    for (subpath, segments) in path.subpaths().iter().zip(&measured) {
+        let subpath_start = position;
+        let mut in_trim = false;
+
+        for segment in segments {
+            let length = segment.length();
+            let (a, b) = (from.max(position), to.min(position + length));
+
+            if a < b {
+                let piece = segment
+                    .segment()
+                    .slice(segment.t_at(a - position), segment.t_at(b - position));
+
+                if !in_trim {
+                    builder.move_to(piece.start());
+                    in_trim = true;
+                }
+
+                builder.segment_to(piece);
+            }
+
+            position += length;
+        }
+
+        if in_trim && subpath.is_closed() && from <= subpath_start && to >= position {
+            builder.close();
+        }
+    }
+
+    // Every point comes from a segment of `path`, which is already valid
+    builder.build().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::Segment;
+    use cgmath::{InnerSpace, Vector2};
+
+    /// A line of length 2 followed by a closed square with sides of length 1.
+    fn line_and_square() -> Path {
+        let mut builder = PathBuilder::new();
+        builder
+            .move_to(Vector2::new(0., 0.))
+            .line_to(Vector2::new(2., 0.))
+            .rect(Vector2::new(0., 1.), Vector2::new(1., 1.));
+
+        builder.build().unwrap()
+    }
+
+    fn endpoints(path: &Path) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+        path.segments()
+            .map(|segment| (segment.start(), segment.end()))
+            .collect()
+    }
+
+    #[test]
+    fn whole_path() {
+        let path = line_and_square();
+
+        assert_eq!(trim(&path, 0., 1.), path);
+        assert_eq!(trim(&path, -1., 2.), path);
+    }
+
+    #[test]
+    fn empty() {
+        let path = line_and_square();
+
+        assert!(trim(&path, 0.5, 0.5).is_empty());
+        assert!(trim(&path, 0.75, 0.25).is_empty());
+    }
+
+    #[test]
+    fn endpoints_at_lengths() {
+        // The total length is 6, so this is from 1 to 5
+        let trimmed = trim(&line_and_square(), 1. / 6., 5. / 6.);
+        let subpaths = trimmed.subpaths();
+
+        assert_eq!(subpaths.len(), 2);
+        assert!(!subpaths[1].is_closed());
+        assert_eq!(
+            endpoints(&trimmed),
+            vec![
+                (Vector2::new(1., 0.), Vector2::new(2., 0.)),
+                (Vector2::new(0., 1.), Vector2::new(1., 1.)),
+                (Vector2::new(1., 1.), Vector2::new(1., 2.)),
+                (Vector2::new(1., 2.), Vector2::new(0., 2.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_subpath_kept_whole() {
+        let trimmed = trim(&line_and_square(), 1. / 6., 1.);
+
+        assert!(trimmed.subpaths()[1].is_closed());
+    }
+
+    #[test]
+    fn quadratic_cut_at_arc_length() {
+        let quadratic = (
+            Vector2::new(0., 0.),
+            Vector2::new(1., 2.),
+            Vector2::new(2., 0.),
+        );
+        let path = Path::from_points(vec![quadratic.0, quadratic.1, quadratic.2]).unwrap();
+        let measured = SegmentLength::new(Segment::Quadratic(quadratic));
+        let trimmed = trim(&path, 0.25, 0.5);
+        let lengths = trimmed
+            .segments()
+            .map(|segment| SegmentLength::new(segment).length())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lengths.len(), 1);
+        assert!((lengths[0] - measured.length() / 4.).abs() < 1e-4);
+        // The curve is symmetric, so halfway along its length is its apex
+        let end = trimmed.segments().last().unwrap().end();
+
+        assert!((end - Vector2::new(1., 1.)).magnitude() < 1e-4);
+    }
+}
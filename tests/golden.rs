@@ -0,0 +1,592 @@
+//! # Golden image tests
+//!
+//! Renders a corpus of curves offscreen and compares them against the reference images in
+//! `tests/golden`. Images are compared perceptually, in the same way as `pixelmatch`: a pixel only
+//! counts as different if the YIQ distance between the two colours is above `COLOR_THRESHOLD`,
+//! and the test only fails if more than `MAX_DIFFERENT_PIXELS` of the pixels are different. This
+//! allows for small differences in rasterisation between adapters.
+//!
+//! No GPU is needed, these are designed to run on a software adapter such as lavapipe (for
+//! example with `VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json`). The GPU test
+//! runs by default on whatever adapter there is. On a machine without one it prints that it was
+//! skipped and passes, unless `REQUIRE_ADAPTER` is set, as it should be in CI, where it fails.
+//!
+//! The CPU rasterizer reproduces the GPU passes, so it is checked against the same references.
+//!
+//! When a case fails the output is written next to the reference as `<name>.actual.png` (or
+//! `<name>.software.png` for the CPU rasterizer). To accept the new output, run the GPU test with
+//! `UPDATE_GOLDEN=1` and check in the new references. Only the GPU test writes references, so
+//! that the CPU rasterizer is never checked against its own output. The references that are
+//! checked in were written by the CPU rasterizer, as they were made without an adapter, and
+//! should be replaced by running the GPU test with `UPDATE_GOLDEN=1` on lavapipe.
+
+use cgmath::{Matrix3, Vector2};
+use quadratic_decomposition::{
+    boolean::{self, Operation},
+    dash, headless,
+    outline::{self, Cap, Join},
+    trim, BlendMode, ColorSpace, Fill, FillRule, Filter, Gradient, Headless, Image, Options, Paint,
+    Path, PathBuilder, Pattern, RgbaImage, SampleCount, Scene, ScenePath, SoftwareCanvas, Spacing,
+    Spread, Stop, Stroke, StrokeStyle,
+};
+use std::{f32::consts::PI, fs, num::NonZeroU8, path::PathBuf, sync::Arc};
+
+const SIZE: (u32, u32) = (128, 128);
+
+/// The maximum YIQ distance between two pixels that are considered the same, from 0 to 1.
+const COLOR_THRESHOLD: f32 = 0.1;
+
+/// The fraction of pixels that can differ before a case fails.
+const MAX_DIFFERENT_PIXELS: f32 = 0.005;
+
+enum Points {
+    /// Quadratics in the implicit format
+    Quadratics(&'static [[f32; 2]]),
+    Polyline(&'static [[f32; 2]]),
+    Built(fn() -> Path),
+    /// A whole scene, drawn with its own paints
+    Scene(fn() -> Scene),
+}
+
+struct Case {
+    name: &'static str,
+    path: Points,
+    num_segments: u8,
+    spacing: Spacing,
+    sample_count: SampleCount,
+    fxaa: bool,
+}
+
+impl Case {
+    /// A case drawn with the default options, which each case can then change.
+    const fn new(name: &'static str, path: Points) -> Self {
+        Self {
+            name,
+            path,
+            num_segments: 2,
+            spacing: Spacing::Parameter,
+            sample_count: SampleCount::One,
+            fxaa: false,
+        }
+    }
+
+    const fn num_segments(self, num_segments: u8) -> Self {
+        Self {
+            num_segments,
+            ..self
+        }
+    }
+
+    const fn spacing(self, spacing: Spacing) -> Self {
+        Self { spacing, ..self }
+    }
+
+    const fn sample_count(self, sample_count: SampleCount) -> Self {
+        Self {
+            sample_count,
+            ..self
+        }
+    }
+
+    const fn fxaa(self) -> Self {
+        Self { fxaa: true, ..self }
+    }
+
+    fn path(&self) -> Path {
+        match self.path {
+            Points::Quadratics(points) => Path::from_points(points.iter().copied()),
+            Points::Polyline(points) => Path::from_polyline(points.iter().copied()),
+            Points::Built(build) => return build(),
+            Points::Scene(_) => unreachable!("{} is drawn as a scene", self.name),
+        }
+        .unwrap()
+    }
+}
+
+const STRAIGHT: &[[f32; 2]] = &[[-0.8, -0.6], [0., 0.], [0.8, 0.6]];
+const HAIRPIN: &[[f32; 2]] = &[[-0.3, -0.7], [0., 1.6], [0.3, -0.7]];
+const S_CURVE: &[[f32; 2]] = &[[-0.8, 0.], [-0.4, 0.8], [0., 0.], [0.4, -0.8], [0.8, 0.]];
+const NEAR_DEGENERATE: &[[f32; 2]] = &[[-0.6, -0.3], [-0.599, -0.299], [0.6, 0.4]];
+// The control point is beyond the end, so the curve doubles back on itself
+const COLLINEAR_CUSP: &[[f32; 2]] = &[[-0.6, -0.3], [0.8, 0.4], [0.2, 0.1]];
+const COINCIDENT_CONTROL: &[[f32; 2]] = &[[-0.6, 0.2], [-0.6, 0.2], [0.6, -0.2]];
+const ZIGZAG: &[[f32; 2]] = &[
+    [-0.8, -0.5],
+    [-0.4, 0.5],
+    [0., -0.5],
+    [0.4, 0.5],
+    [0.8, -0.5],
+];
+
+fn shapes() -> Path {
+    let mut builder = Path::builder();
+
+    builder
+        .circle(Vector2::new(-0.45, 0.45), 0.35)
+        .rounded_rect(
+            Vector2::new(0.1, 0.1),
+            Vector2::new(0.7, 0.7),
+            Vector2::new(0.2, 0.1),
+        )
+        .ellipse(Vector2::new(-0.45, -0.45), Vector2::new(0.35, 0.2))
+        .move_to(Vector2::new(0.45, -0.15))
+        .arc(
+            Vector2::new(0.45, -0.45),
+            Vector2::new(0.3, 0.3),
+            0.,
+            PI / 2.,
+            1.5 * PI,
+        );
+
+    builder.build().unwrap()
+}
+
+/// An open wave above a circle, for cutting up by arc length
+fn wave_and_circle() -> Path {
+    let mut builder = Path::builder();
+
+    builder
+        .move_to(Vector2::new(-0.8, 0.3))
+        .quad_to(Vector2::new(-0.4, 1.1), Vector2::new(0., 0.3))
+        .quad_to(Vector2::new(0.4, -0.5), Vector2::new(0.8, 0.3))
+        .circle(Vector2::new(0., -0.5), 0.35);
+
+    builder.build().unwrap()
+}
+
+fn dashed() -> Path {
+    dash::dash(&wave_and_circle(), &[0.25, 0.1, 0.05], 0.1)
+}
+
+fn trimmed() -> Path {
+    trim::trim(&wave_and_circle(), 0.2, 0.8)
+}
+
+/// The parts of a circle and a rounded rectangle that are in exactly one of them
+fn xor() -> Path {
+    let (mut a, mut b) = (Path::builder(), Path::builder());
+
+    a.circle(Vector2::new(-0.2, 0.1), 0.55);
+    b.rounded_rect(
+        Vector2::new(-0.1, -0.7),
+        Vector2::new(0.8, 0.9),
+        Vector2::new(0.1, 0.1),
+    );
+
+    boolean::boolean(
+        &a.build().unwrap(),
+        &b.build().unwrap(),
+        Operation::Xor,
+        FillRule::NonZero,
+    )
+}
+
+/// The outline of a stroke with every kind of corner, drawn as a thin stroke itself
+fn outlined() -> Path {
+    let mut builder = Path::builder();
+
+    builder
+        .move_to(Vector2::new(-0.8, -0.1))
+        .line_to(Vector2::new(-0.4, 0.5))
+        .quad_to(Vector2::new(0., 0.9), Vector2::new(0.3, 0.3))
+        .line_to(Vector2::new(0.8, 0.6))
+        .rect(Vector2::new(-0.6, -0.8), Vector2::new(1.2, 0.4));
+
+    let style = StrokeStyle {
+        width: 0.2,
+        join: Join::Miter(4.),
+        cap: Cap::Round,
+    };
+
+    outline::outline(&builder.build().unwrap(), &style, &Options::default())
+}
+
+/// Circles blended onto a rectangle, one punching a hole in it and one only drawn where it is,
+/// and a rectangle drawn behind everything
+fn blend_modes() -> Scene {
+    let filled = |path: Path, color, blend| ScenePath {
+        path,
+        fill: Some(Fill {
+            paint: Paint::Solid(color),
+            rule: FillRule::NonZero,
+        }),
+        stroke: None,
+        blend,
+    };
+    let circle = |x, y, radius| {
+        let mut builder = Path::builder();
+
+        builder.circle(Vector2::new(x, y), radius);
+        builder.build().unwrap()
+    };
+    let rect = |x, y, width, height| {
+        let mut builder = Path::builder();
+
+        builder.rect(Vector2::new(x, y), Vector2::new(width, height));
+        builder.build().unwrap()
+    };
+
+    Scene::new(
+        [0., 0., 100., 100.],
+        vec![
+            filled(
+                rect(5., 5., 90., 55.),
+                [0.8, 0.8, 0.2, 1.],
+                BlendMode::Normal,
+            ),
+            filled(
+                circle(25., 30., 15.),
+                [0.2, 0.6, 1., 1.],
+                BlendMode::Multiply,
+            ),
+            filled(
+                circle(50., 30., 15.),
+                [0.8, 0.2, 0.2, 1.],
+                BlendMode::Screen,
+            ),
+            filled(circle(75., 30., 15.), [0., 0.4, 0., 1.], BlendMode::Add),
+            filled(
+                circle(40., 60., 12.),
+                [0., 0., 0., 1.],
+                BlendMode::DestinationOut,
+            ),
+            filled(
+                circle(80., 60., 12.),
+                [1., 0., 0.5, 1.],
+                BlendMode::SourceAtop,
+            ),
+            filled(
+                rect(10., 50., 80., 40.),
+                [0.2, 0.2, 0.8, 1.],
+                BlendMode::DestinationOver,
+            ),
+        ],
+    )
+}
+
+/// A square and a circle filled with the first two paints and a wave stroked with the third,
+/// for comparing paints with each shape of geometry
+fn square_circle_wave(square_paint: Paint, circle_paint: Paint, wave_paint: Paint) -> Scene {
+    let (mut square, mut circle, mut wave) = (Path::builder(), Path::builder(), Path::builder());
+
+    square.rect(Vector2::new(10., 10.), Vector2::new(35., 35.));
+    circle.circle(Vector2::new(72., 28.), 20.);
+    wave.move_to(Vector2::new(10., 75.))
+        .quad_to(Vector2::new(50., 45.), Vector2::new(90., 75.));
+
+    let filled = |path: PathBuilder, paint| ScenePath {
+        path: path.build().unwrap(),
+        fill: Some(Fill {
+            paint,
+            rule: FillRule::NonZero,
+        }),
+        stroke: None,
+        blend: BlendMode::Normal,
+    };
+
+    Scene::new(
+        [0., 0., 100., 100.],
+        vec![
+            filled(square, square_paint),
+            filled(circle, circle_paint),
+            ScenePath {
+                path: wave.build().unwrap(),
+                fill: None,
+                stroke: Some(Stroke {
+                    paint: wave_paint,
+                    width: 8.,
+                    dash_array: Vec::new(),
+                    dash_offset: 0.,
+                    trim_start: 0.,
+                    trim_end: 1.,
+                }),
+                blend: BlendMode::Normal,
+            },
+        ],
+    )
+}
+
+/// A checkerboard repeated without filtering, the same image rotated, scaled up and reflected
+/// with filtering, and a translucent gradient image padded along a stroke
+fn patterns() -> Scene {
+    let checker = (0..16)
+        .map(|i| match (i % 4 + i / 4) % 2 {
+            0 => [255, 255, 255, 255],
+            _ => [0, 96, 192, 255],
+        })
+        .collect();
+    let checker = Arc::new(Image::new(4, 4, checker).unwrap());
+    let fade = (0..8).map(|i| [255, 128, 0, 255 - i as u8 * 32]).collect();
+    let fade = Arc::new(Image::new(8, 1, fade).unwrap());
+
+    let (sin, cos) = (PI / 6.).sin_cos();
+    let nearest = Pattern {
+        filter: Filter::Nearest,
+        ..Pattern::new(
+            checker.clone(),
+            Matrix3::new(2., 0., 0., 0., 2., 0., 10., 10., 1.),
+        )
+    };
+    let reflected = Pattern {
+        spread: Spread::Reflect,
+        ..Pattern::new(
+            checker,
+            Matrix3::new(
+                4. * cos,
+                4. * sin,
+                0.,
+                -4. * sin,
+                4. * cos,
+                0.,
+                72.,
+                28.,
+                1.,
+            ),
+        )
+    };
+    let padded = Pattern {
+        spread: Spread::Pad,
+        ..Pattern::new(fade, Matrix3::new(8., 0., 0., 0., 8., 0., 18., 0., 1.))
+    };
+
+    square_circle_wave(nearest.into(), reflected.into(), padded.into())
+}
+
+/// A repeating linear gradient, a reflected radial one and a padded one along a stroke
+fn gradients() -> Scene {
+    let stop = |offset, color| Stop { offset, color };
+    let linear = Gradient {
+        spread: Spread::Repeat,
+        ..Gradient::linear(
+            Vector2::new(10., 10.),
+            Vector2::new(22., 22.),
+            vec![stop(0., [1., 0., 0., 1.]), stop(1., [0., 0., 1., 1.])],
+        )
+    };
+    let radial = Gradient {
+        spread: Spread::Reflect,
+        ..Gradient::radial(
+            Vector2::new(72., 28.),
+            8.,
+            vec![stop(0., [1., 1., 0., 1.]), stop(1., [0., 0.5, 0., 0.5])],
+        )
+    };
+    let padded = Gradient::linear(
+        Vector2::new(20., 0.),
+        Vector2::new(80., 0.),
+        vec![
+            stop(0., [1., 1., 1., 1.]),
+            stop(0.5, [1., 0., 1., 1.]),
+            stop(0.5, [0., 1., 1., 1.]),
+        ],
+    );
+
+    square_circle_wave(linear.into(), radial.into(), padded.into())
+}
+
+const CASES: &[Case] = &[
+    Case::new("straight", Points::Quadratics(STRAIGHT)),
+    Case::new("hairpin", Points::Quadratics(HAIRPIN)),
+    Case::new("hairpin_8_segments", Points::Quadratics(HAIRPIN)).num_segments(8),
+    Case::new("hairpin_arc_length", Points::Quadratics(HAIRPIN))
+        .num_segments(4)
+        .spacing(Spacing::ArcLength),
+    Case::new("s_curve_1_segment", Points::Quadratics(S_CURVE)).num_segments(1),
+    Case::new("s_curve", Points::Quadratics(S_CURVE)),
+    Case::new("s_curve_4_segments", Points::Quadratics(S_CURVE)).num_segments(4),
+    Case::new("near_degenerate", Points::Quadratics(NEAR_DEGENERATE)),
+    Case::new("collinear_cusp", Points::Quadratics(COLLINEAR_CUSP)),
+    Case::new("coincident_control", Points::Quadratics(COINCIDENT_CONTROL)),
+    Case::new("polyline", Points::Polyline(ZIGZAG)),
+    Case::new("shapes", Points::Built(shapes)),
+    Case::new("dashed", Points::Built(dashed)),
+    Case::new("trimmed", Points::Built(trimmed)),
+    Case::new("xor", Points::Built(xor)),
+    Case::new("outlined", Points::Built(outlined)),
+    Case::new("shapes_msaa", Points::Built(shapes)).sample_count(SampleCount::Four),
+    Case::new("shapes_fxaa", Points::Built(shapes)).fxaa(),
+    Case::new("gradients", Points::Scene(gradients)),
+    Case::new("blend_modes", Points::Scene(blend_modes)),
+    Case::new("patterns", Points::Scene(patterns)),
+];
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn read_png(path: &std::path::Path) -> Option<RgbaImage> {
+    let (info, mut reader) = png::Decoder::new(fs::File::open(path).ok()?)
+        .read_info()
+        .ok()?;
+
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::RGBA, png::BitDepth::Eight),
+        "{} must be an 8-bit RGBA PNG",
+        path.display()
+    );
+
+    let mut data = vec![0; info.buffer_size()];
+    reader.next_frame(&mut data).ok()?;
+
+    Some(RgbaImage {
+        width: info.width,
+        height: info.height,
+        data,
+    })
+}
+
+/// Converts a pixel to YIQ after blending it onto white, as in `pixelmatch`.
+fn yiq(pixel: &[u8]) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.;
+    let blend = |c: u8| 255. + (c as f32 - 255.) * alpha;
+    let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+
+    [
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23,
+        r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+    ]
+}
+
+/// The number of pixels that are perceptually different between the two images.
+fn count_different_pixels(a: &RgbaImage, b: &RgbaImage) -> usize {
+    // The largest possible value of the distance below, between black and white
+    const MAX_DELTA: f32 = 35215.;
+
+    a.data
+        .chunks(4)
+        .zip(b.data.chunks(4))
+        .filter(|(a, b)| {
+            let ([ay, ai, aq], [by, bi, bq]) = (yiq(a), yiq(b));
+            let delta =
+                0.5053 * (ay - by).powi(2) + 0.299 * (ai - bi).powi(2) + 0.1957 * (aq - bq).powi(2);
+
+            delta > MAX_DELTA * COLOR_THRESHOLD.powi(2)
+        })
+        .count()
+}
+
+/// Renders every case with `render` and compares it against the reference, or overwrites the
+/// reference if `update` is set. Failing output is written with the given suffix.
+fn check_cases(mut render: impl FnMut(&Case, &Options) -> RgbaImage, suffix: &str, update: bool) {
+    let dir = golden_dir();
+    let mut failures = Vec::new();
+
+    for case in CASES {
+        let options = Options {
+            num_segments: NonZeroU8::new(case.num_segments).unwrap(),
+            spacing: case.spacing,
+            sample_count: case.sample_count,
+            fxaa: case.fxaa,
+            color_space: ColorSpace::Srgb,
+            clear_color: [0., 0., 0., 0.],
+            validate: true,
+        };
+        let actual = render(case, &options);
+
+        let reference_path = dir.join(format!("{}.png", case.name));
+        let actual_path = dir.join(format!("{}.{}.png", case.name, suffix));
+
+        if update {
+            fs::create_dir_all(&dir).unwrap();
+            actual.save_png(&reference_path).unwrap();
+            continue;
+        }
+
+        let reference = match read_png(&reference_path) {
+            Some(reference) => reference,
+            None => {
+                failures.push(format!("{}: missing reference image", case.name));
+                continue;
+            }
+        };
+
+        let message = if (reference.width, reference.height) != (actual.width, actual.height) {
+            Some(format!(
+                "{}: expected {}x{}, got {}x{}",
+                case.name, reference.width, reference.height, actual.width, actual.height
+            ))
+        } else {
+            let different = count_different_pixels(&reference, &actual);
+            let total = (actual.width * actual.height) as f32;
+
+            if different as f32 > total * MAX_DIFFERENT_PIXELS {
+                Some(format!(
+                    "{}: {} pixels ({:.2}%) differ",
+                    case.name,
+                    different,
+                    different as f32 / total * 100.
+                ))
+            } else {
+                None
+            }
+        };
+
+        match message {
+            Some(message) => {
+                actual.save_png(&actual_path).unwrap();
+                failures.push(message);
+            }
+            None => {
+                let _ = fs::remove_file(&actual_path);
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Golden images differ (run the GPU test with UPDATE_GOLDEN=1 to accept the new output):\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn golden_images() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut headless = match futures::executor::block_on(Headless::new(SIZE)) {
+        Ok(headless) => headless,
+        Err(headless::Error::NoAdapter)
+            if !update && std::env::var_os("REQUIRE_ADAPTER").is_none() =>
+        {
+            eprintln!(
+                "Skipping the GPU golden image tests as there is no adapter. Install a software \
+                 adapter such as lavapipe to run them, or set REQUIRE_ADAPTER to fail instead."
+            );
+
+            return;
+        }
+        Err(e) => panic!("The GPU golden image tests need an adapter: {}", e),
+    };
+
+    check_cases(
+        |case, options| {
+            match case.path {
+                Points::Scene(scene) => headless.render_scene(&scene(), options),
+                _ => headless.render(&case.path(), options),
+            }
+            .unwrap()
+        },
+        "actual",
+        update,
+    );
+}
+
+#[test]
+fn golden_images_software() {
+    let mut canvas = SoftwareCanvas::new(SIZE);
+
+    check_cases(
+        |case, options| {
+            match case.path {
+                Points::Scene(scene) => canvas.render_scene(&scene(), options),
+                _ => canvas.render(&case.path(), options),
+            }
+            .unwrap()
+        },
+        "software",
+        false,
+    );
+}